@@ -0,0 +1,55 @@
+use std::fmt::{Debug, Write};
+
+use {Cell, Reactor};
+
+impl <'a, T: Clone + PartialEq + Debug> Reactor<'a, T> {
+    // Renders the cells of the reactor as a Graphviz DOT digraph, for reviewing its structure.
+    //
+    // Input cells are drawn as boxes and compute cells as ellipses, each showing its ID, label,
+    // value and number of callbacks, along with the name of its function and whether it's stale
+    // for compute cells. Edges go from each dependency to the cell depending on it, labelled with
+    // the index of the argument the dependency is passed as.
+    //
+    // Stale lazy cells show their outdated value, as rendering the graph doesn't compute anything.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph {\n");
+        for node in self.dep_graph.node_indices() {
+            let cell = &self.dep_graph[node];
+            let mut label = format!("#{}", node.index());
+            if let Some(name) = cell.label() {
+                write!(label, " {:?}", name).unwrap();
+            }
+            let (shape, callbacks) = match *cell {
+                Cell::Input(ref input) => ("box", input.callbacks.len()),
+                Cell::Computed(ref computed) => {
+                    if let Some(ref name) = computed.function_name {
+                        write!(label, " {}", name).unwrap();
+                    }
+                    ("ellipse", computed.callbacks.len())
+                },
+            };
+            match cell.value() {
+                Ok(value) => write!(label, "\n= {:?}", value).unwrap(),
+                Err(error) => write!(label, "\n#ERR {}", error.error()).unwrap(),
+            }
+            if self.needs_refresh(node) {
+                label.push_str(" (stale)");
+            }
+            if callbacks > 0 {
+                write!(label, "\ncallbacks: {}", callbacks).unwrap();
+            }
+            writeln!(dot, "    n{} [shape={}, label=\"{}\"];", node.index(), shape, escape(&label)).unwrap();
+        }
+        for edge in self.dep_graph.edge_indices() {
+            let (dependant, dependency) = self.dep_graph.edge_endpoints(edge).unwrap();
+            writeln!(dot, "    n{} -> n{} [label=\"{}\"];", dependency.index(), dependant.index(), self.dep_graph[edge]).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// Escapes text for use within a double-quoted DOT string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+
+use CellID;
+
+// A write to an input cell, which can be reverted by writing its old value back.
+#[derive(Debug)]
+pub struct Edit<T> {
+    pub id: CellID,
+    pub old_value: T,
+    pub new_value: T,
+}
+
+// The writes made by a single `set_value` call or transaction, which are undone and redone together.
+pub type Step<T> = Vec<Edit<T>>;
+
+// The undo and redo stacks of a Reactor, see `Reactor::set_history_depth`.
+#[derive(Debug)]
+pub struct History<T> {
+    undo: VecDeque<Step<T>>,
+    redo: Vec<Step<T>>,
+    // how many steps can be undone, beyond which the oldest steps are forgotten.
+    depth: usize,
+}
+
+impl <T> History<T> {
+    pub fn new(depth: usize) -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            depth,
+        }
+    }
+
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.undo.len() > depth {
+            self.undo.pop_front();
+        }
+    }
+
+    // Records a new step, which can no longer be followed by the steps that were undone before it.
+    pub fn record(&mut self, step: Step<T>) {
+        if step.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        self.push_undo(step);
+    }
+
+    pub fn push_undo(&mut self, step: Step<T>) {
+        self.undo.push_back(step);
+        if self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Step<T>> {
+        self.undo.pop_back()
+    }
+
+    pub fn push_redo(&mut self, step: Step<T>) {
+        self.redo.push(step);
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Step<T>> {
+        self.redo.pop()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
@@ -1,17 +1,97 @@
-#[allow(unused_variables)]
+// `#[derive(Fail)]` emits its trait impls inside an anonymous const.
+#![allow(non_local_definitions)]
 
 extern crate petgraph;
 #[macro_use]
 extern crate failure;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
 
-use std::collections::{HashMap, HashSet, BTreeMap};
-use petgraph::graph::{Graph, NodeIndex};
+use std::cell::OnceCell;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, BTreeMap, BinaryHeap};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use petgraph::graph::NodeIndex;
+use petgraph::stable_graph::StableGraph;
 use petgraph::Direction;
-use failure::ResultExt;
 
-pub type CellID = NodeIndex;
+use history::{Edit, History, Step};
+use observer::ObserverSlot;
+use stats::StatsTable;
+
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(feature = "serde")]
+mod persist;
+mod dot;
+mod history;
+mod observer;
+mod registry;
+mod stats;
+mod sync;
+mod typed;
+
+#[cfg(feature = "serde")]
+pub use persist::SavedReactor;
+pub use observer::ReactorObserver;
+pub use registry::{ComputeRegistry, NamedComputeFunc};
+pub use stats::CellStats;
+pub use sync::{SendReactor, SharedReactor};
+pub use typed::{AnyValue, CellHandle, ComputeHandle, Dependencies, InputHandle, TypedReactor, TypedTransaction};
+
 pub type CallbackID = u32;
 
+// Identifies a cell within a Reactor.
+//
+// A cell's ID remains valid for as long as the cell exists, regardless of other cells being
+// created or removed, and is never reused once the cell itself is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CellID {
+    node: NodeIndex,
+    // tells apart cells that occupied the same graph node at different times.
+    generation: u32,
+}
+
+impl CellID {
+    // Gets the index of the graph node holding the cell.
+    pub fn index(&self) -> usize {
+        self.node.index()
+    }
+}
+
+type ComputeFunc<T> = Box<dyn Fn(&[&T]) -> Result<T, failure::Error>>;
+// Called with the ID of the changed cell, its previous value, and its new value.
+type Callback<'a, T> = Box<dyn FnMut(CellID, Result<&T, &CellError>, Result<&T, &CellError>) + 'a>;
+// Tells whether a compute cell's previous and new values are the same, see `Reactor::set_comparator`.
+type Comparator<T> = Box<dyn Fn(&T, &T) -> bool>;
+// A cell whose value has changed during a propagation, along with its previous value.
+type Change<T> = (NodeIndex, Result<T, CellError>);
+
+// A recomputation of a cell, made of its compute function and the values of its dependencies.
+type Job<'r, T> = (&'r ComputeFunc<T>, Vec<&'r T>);
+// The result of running a compute function, along with how long it took.
+type Timed<T> = (Result<T, failure::Error>, Duration);
+// Runs the jobs of a propagation level, returning their results in the same order.
+type LevelEvaluator<T> = for<'r> fn(Vec<Job<'r, T>>) -> Vec<Timed<T>>;
+
+// Runs the jobs of a propagation level one after the other, on the calling thread.
+fn evaluate_sequentially<T>(jobs: Vec<Job<T>>) -> Vec<Timed<T>> {
+    jobs.into_iter()
+        .map(|(compute_func, values)| run_timed(compute_func, &values))
+        .collect()
+}
+
+// Runs a compute function, timing it.
+fn run_timed<T>(compute_func: &ComputeFunc<T>, values: &[&T]) -> Timed<T> {
+    let start = Instant::now();
+    let result = compute_func(values);
+    (result, start.elapsed())
+}
+
 #[derive(Debug)]
 pub struct Reactor<'a, T> {
     /* A directed graph where each node is a cell pointing towards its dependencies
@@ -21,31 +101,97 @@ pub struct Reactor<'a, T> {
 
        The edge weight represents the index of a dependency cell in the argument list of the compute function.
        (Needed because the insertion order of the edges isn't preserved when walking over neighbor nodes)
+
+       A stable graph is used so that removing cells doesn't shift the indices of the remaining ones.
     */
-    pub dep_graph: Graph<Cell<'a, T>, usize>,
+    dep_graph: StableGraph<Cell<'a, T>, usize>,
     // an increasing counter of used callback IDs.
     cur_callback_id: CallbackID,
+    // an increasing counter of cell generations, one for every created cell.
+    cur_generation: u32,
+    // how the compute functions of cells of the same height are run during propagation.
+    evaluate_level: LevelEvaluator<T>,
+    // the changes to input cells that can be undone, if enabled through `set_history_depth`.
+    history: Option<History<T>>,
+    // the compute functions that cells can be created from by name.
+    registry: ComputeRegistry<T>,
+    // the cells that have a label, by their label.
+    labels: HashMap<String, NodeIndex>,
+    // the cells changed by the last propagation, each along with the dependency whose change
+    // caused its own, see `explain`.
+    trace: HashMap<NodeIndex, Option<NodeIndex>>,
+    observer: ObserverSlot<'a, T>,
+    // how much work each cell took, if enabled through `set_stats_enabled`.
+    stats: Option<StatsTable>,
 }
 
 #[derive(Debug)]
-pub enum Cell<'a, T> {
-    Input(InputCell<T>),
+enum Cell<'a, T> {
+    Input(InputCell<'a, T>),
     Computed(ComputedCell<'a, T>)
 }
 
-#[derive(Debug)]
-pub struct InputCell<T> {
-    value: T
+struct InputCell<'a, T> {
+    value: T,
+    generation: u32,
+    label: Option<String>,
+    callbacks: HashMap<CallbackID, Callback<'a, T>>,
 }
 
-pub struct ComputedCell<'a, T> {
-    value: T,
-    compute_func: Box<Fn(&[T]) -> T>,
-    callbacks: HashMap<CallbackID, Box<FnMut(T) -> () + 'a>>,
+impl <'a, T: std::fmt::Debug> ::std::fmt::Debug for InputCell<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Input {{ label: {:?}, value: {:?}, callbacks_len: {} }}", self.label, self.value, self.callbacks.len())
+    }
+}
+
+struct ComputedCell<'a, T> {
+    // the value of the cell, or the error that prevented computing it.
+    value: Result<T, CellError>,
+    generation: u32,
+    label: Option<String>,
+    // the length of the longest dependency path from this cell down to an input cell.
+    // A cell's height is always greater than the heights of all of its dependencies, so
+    // recomputing cells in increasing height order never reads a stale dependency.
+    height: usize,
+    compute_func: ComputeFunc<T>,
+    callbacks: HashMap<CallbackID, Callback<'a, T>>,
+    // the name of the registered function the cell was created from, see `create_compute_named`.
+    function_name: Option<String>,
+    // decides whether the cell's value has changed, instead of comparing values with `PartialEq`.
+    comparator: Option<Comparator<T>>,
+    // whether the cell is only recomputed when its value is needed, rather than whenever its
+    // dependencies change. Lazy cells with callbacks are always kept up to date, like eager ones.
+    lazy: bool,
+    // whether `value` is outdated, which only ever happens to lazy cells.
+    stale: bool,
+    // the up to date value of a stale cell, once it's computed on demand.
+    fresh: OnceCell<Result<T, CellError>>,
 }
 impl <'a, T: std::fmt::Debug> ::std::fmt::Debug for ComputedCell<'a, T> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Computed {{ value: {:?}, callbacks_len: {} }}", self.value, self.callbacks.len())
+        write!(f, "Computed {{ label: {:?}, value: {:?}, function: {:?}, height: {}, lazy: {}, stale: {}, callbacks_len: {}, custom_comparator: {} }}",
+               self.label, self.value, self.function_name, self.height, self.lazy, self.stale, self.callbacks.len(), self.comparator.is_some())
+    }
+}
+
+impl <'a, T> ComputedCell<'a, T> {
+    // Moves a value computed on demand into the cell, making it up to date.
+    fn settle(&mut self) {
+        if let Some(value) = self.fresh.take() {
+            self.value = value;
+            self.stale = false;
+        }
+    }
+}
+
+impl <'a, T: PartialEq> ComputedCell<'a, T> {
+    // Tells whether a recomputed value is the same as the cell's current one, using the cell's
+    // comparator if it has one. Errors and values are always different from one another.
+    fn unchanged(&self, new_value: &Result<T, CellError>) -> bool {
+        match (&self.value, new_value, &self.comparator) {
+            (Ok(old), Ok(new), Some(comparator)) => comparator(old, new),
+            (old, new, _) => old == new,
+        }
     }
 }
 
@@ -54,77 +200,673 @@ impl <'a, T: std::fmt::Debug> ::std::fmt::Debug for ComputedCell<'a, T> {
 pub enum ReactError {
     #[fail(display = "No cell with ID {:?} was found", id)]
     MissingCell { id: CellID},
-    #[fail(display = "Expected input cell at ID {:?}, found computed cell", id)]
-    ExpectedInputCell { id: CellID },
-    #[fail(display = "Expected computed cell at ID {:?}, found input cell", id)]
-    ExpectedComputedCell { id: CellID },
+    #[fail(display = "Expected input cell at ID {:?}{}, found computed cell", id, label)]
+    ExpectedInputCell { id: CellID, label: CellLabel },
+    #[fail(display = "Expected computed cell at ID {:?}{}, found input cell", id, label)]
+    ExpectedComputedCell { id: CellID, label: CellLabel },
     #[fail(display = "Can't create computed cell as its missing the following dependencies: {:?}", missing_deps)]
     MissingDepedencies { missing_deps: Vec<CellID>},
     #[fail(display = "Can't delete a callback at ID {:?} as it doesn't exist", id)]
     CallbackDoesntExist { id: CallbackID },
+    #[fail(display = "The cell with ID {:?} was removed", id)]
+    RemovedCell { id: CellID },
+    #[fail(display = "Can't remove cell with ID {:?}{} as the following cells depend on it: {}", id, label, labelled_dependants)]
+    CellInUse { id: CellID, label: CellLabel, dependants: Vec<CellID>, labelled_dependants: CellList },
+    #[fail(display = "Can't make cell with ID {:?}{} depend on the following cells, as they depend on it: {}", id, label, labelled_cyclic_deps)]
+    DependencyCycle { id: CellID, label: CellLabel, cyclic_deps: Vec<CellID>, labelled_cyclic_deps: CellList },
+    #[fail(display = "No compute function is registered under the name {:?}", name)]
+    UnknownFunction { name: String },
+    #[fail(display = "Can't save computed cell with ID {:?}{} as it wasn't created from a named function", id, label)]
+    UnnamedFunction { id: CellID, label: CellLabel },
+    #[fail(display = "Can't load the saved reactor: {}", reason)]
+    InvalidSave { reason: String },
+    #[fail(display = "Can't label a cell {:?}, as the cell with ID {:?} already has that label", label, id)]
+    DuplicateLabel { label: String, id: CellID },
+    #[fail(display = "Expected the cell with ID {:?} to hold values of type {}, found {}", id, expected, found)]
+    UnexpectedType { id: CellID, expected: &'static str, found: &'static str },
+}
+
+// The label of the cell an error is about, if it has one (see `Reactor::set_label`), which is
+// displayed after the cell's ID.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CellLabel(pub Option<String>);
+
+impl fmt::Display for CellLabel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(ref label) => write!(f, " ({:?})", label),
+            None => Ok(()),
+        }
+    }
+}
+
+// The cells an error lists, along with their labels, displayed as a list of their IDs, each
+// followed by its label.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CellList(pub Vec<(CellID, CellLabel)>);
+
+impl fmt::Display for CellList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[")?;
+        for (ix, &(id, ref label)) in self.0.iter().enumerate() {
+            if ix > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:?}{}", id, label)?;
+        }
+        write!(f, "]")
+    }
+}
+
+// The error state of a compute cell whose compute function failed, or that depends on such a cell.
+//
+// Like `#ERR` in a spreadsheet, the error propagates to every cell depending on the failed cell,
+// without their compute functions being called, until the failed cell's value can be computed
+// again. All of these cells share the same error.
+#[derive(Debug, Clone)]
+pub struct CellError {
+    origin: CellID,
+    error: Arc<failure::Error>,
+}
+
+impl CellError {
+    fn new(origin: CellID, error: failure::Error) -> Self {
+        CellError {
+            origin,
+            error: Arc::new(error),
+        }
+    }
+
+    // Gets the ID of the cell whose compute function failed.
+    pub fn origin(&self) -> CellID {
+        self.origin
+    }
+
+    // Gets the error returned by the failed compute function.
+    pub fn error(&self) -> &failure::Error {
+        &self.error
+    }
+}
+
+// A cell failing again in the same way isn't a change, so errors are compared by their origin and
+// message.
+impl PartialEq for CellError {
+    fn eq(&self, other: &Self) -> bool {
+        self.origin == other.origin &&
+            (Arc::ptr_eq(&self.error, &other.error) || self.error.to_string() == other.error.to_string())
+    }
+}
+
+impl fmt::Display for CellError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cell with ID {:?} failed to compute its value: {}", self.origin, self.error)
+    }
+}
+
+// Determines what happens to the cells depending on a cell being removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalMode {
+    // Refuse to remove the cell if any other cell depends on it.
+    Restrict,
+    // Also remove every cell that depends on the removed cell, directly or indirectly.
+    Cascade,
+}
+
+// The kind of a cell, see `Reactor::cell_kind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellKind {
+    Input,
+    Computed,
+    // a compute cell created through `create_lazy_compute`.
+    LazyComputed,
 }
 
 impl <'a, T> Cell<'a, T> {
-    // Gets the (cached) value for the given cell.
-    pub fn value(&self) -> &T {
-        match self {
-            &Cell::Input(ref cell) => &cell.value,
-            &Cell::Computed(ref cell) => &cell.value,
+    // Gets the (cached) value for the given cell, or the error that prevented computing it.
+    // This may be outdated for lazy cells which weren't needed since their dependencies changed,
+    // see `Reactor::result` for getting the up to date value.
+    fn value(&self) -> Result<&T, &CellError> {
+        match *self {
+            Cell::Input(ref cell) => Ok(&cell.value),
+            Cell::Computed(ref cell) => cell.fresh.get().unwrap_or(&cell.value).as_ref(),
+        }
+    }
+
+    // Gets the generation of the given cell, which is unique among all cells of its reactor.
+    fn generation(&self) -> u32 {
+        match *self {
+            Cell::Input(ref cell) => cell.generation,
+            Cell::Computed(ref cell) => cell.generation,
+        }
+    }
+
+    // Gets the height of the given cell in the dependency graph, input cells being at height 0.
+    fn height(&self) -> usize {
+        match *self {
+            Cell::Input(_) => 0,
+            Cell::Computed(ref cell) => cell.height,
+        }
+    }
+
+    // Gets the label of the given cell, if it has one.
+    fn label(&self) -> Option<&str> {
+        match *self {
+            Cell::Input(ref cell) => cell.label.as_deref(),
+            Cell::Computed(ref cell) => cell.label.as_deref(),
+        }
+    }
+
+    fn label_mut(&mut self) -> &mut Option<String> {
+        match *self {
+            Cell::Input(ref mut cell) => &mut cell.label,
+            Cell::Computed(ref mut cell) => &mut cell.label,
         }
     }
 }
 
 
-// You are guaranteed that Reactor will only be tested against types that are Copy + PartialEq.
-impl <'a, T: Copy + PartialEq> Reactor<'a, T> {
+// Values are only cloned when they're read out of the reactor through `value`, so cheaply
+// cloneable types (or values shared behind an `Rc`/`Arc`) are preferable for large values.
+impl <'a, T: Clone + PartialEq> Default for Reactor<'a, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl <'a, T: Clone + PartialEq> Reactor<'a, T> {
     pub fn new() -> Self {
         Reactor {
-            dep_graph: Graph::new(),
+            dep_graph: StableGraph::new(),
             cur_callback_id: 0,
+            cur_generation: 0,
+            evaluate_level: evaluate_sequentially,
+            history: None,
+            registry: ComputeRegistry::new(),
+            labels: HashMap::new(),
+            trace: HashMap::new(),
+            observer: ObserverSlot::new(),
+            stats: None,
         }
     }
 
+    // Creates a reactor whose compute cells can be created from the functions of the given
+    // registry, see `create_compute_named`.
+    pub fn with_registry(registry: ComputeRegistry<T>) -> Self {
+        Reactor {
+            registry,
+            ..Self::new()
+        }
+    }
+
+    // Sets the observer which is told about the events of the reactor, such as propagations and
+    // recomputations, replacing the previous one, see `ReactorObserver`.
+    pub fn set_observer(&mut self, observer: Box<dyn ReactorObserver<T> + 'a>) {
+        self.observer.replace(Some(observer));
+    }
+
+    // Removes the observer of the reactor, giving it back.
+    pub fn take_observer(&mut self) -> Option<Box<dyn ReactorObserver<T> + 'a>> {
+        self.observer.replace(None)
+    }
+
     // Creates an input cell with the specified initial value, returning its ID.
     pub fn create_input(&mut self, initial: T) -> CellID {
-        let input = InputCell { value: initial };
-        self.dep_graph.add_node(Cell::Input(input))
+        let generation = self.next_generation();
+        let input = InputCell { value: initial, generation, label: None, callbacks: HashMap::new() };
+        let node = self.dep_graph.add_node(Cell::Input(input));
+        let id = CellID { node, generation };
+        self.observer.observe(|observer| observer.input_created(id));
+        id
     }
 
     // Creates a compute cell with the specified dependencies and compute function.
-    // The compute function is expected to take in its arguments in the same order as specified in
-    // `dependencies`.
+    // The compute function is expected to take in references to its arguments in the same order as
+    // specified in `dependencies`.
     // You do not need to reject compute functions that expect more arguments than there are
     // dependencies (how would you check for this, anyway?).
     //
     // Return an Err (and you can change the error type) if any dependency doesn't exist.
     //
-    // Notice that there is no way to *remove* a cell.
-    // This means that you may assume, without checking, that if the dependencies exist at creation
-    // time they will continue to exist as long as the Reactor exists.
-    pub fn create_compute<F: 'static + Fn(&[T]) -> T>(&mut self, dependencies: &[CellID], compute_func: F) -> Result<CellID, ReactError> {
+    // A cell can't be removed while this cell depends on it (see `remove_cell`), so the
+    // dependencies will exist for as long as this cell does.
+    pub fn create_compute<F: 'static + Fn(&[&T]) -> T>(&mut self, dependencies: &[CellID], compute_func: F) -> Result<CellID, ReactError> {
+        self.add_compute_cell(dependencies, Box::new(move |values| Ok(compute_func(values))), false)
+    }
+
+    // Creates a compute cell whose compute function may fail.
+    //
+    // When it fails, the cell, along with all cells depending on it, enters an error state (see
+    // `CellError`) until a later change lets the compute function succeed. The error state can be
+    // observed through `result`, and callbacks added through `add_result_callback`.
+    pub fn create_fallible_compute<F, E>(&mut self, dependencies: &[CellID], compute_func: F) -> Result<CellID, ReactError>
+        where F: 'static + Fn(&[&T]) -> Result<T, E>, E: Into<failure::Error>
+    {
+        self.add_compute_cell(dependencies, Box::new(move |values| compute_func(values).map_err(Into::into)), false)
+    }
+
+    // Registers a compute function under the given name in the reactor's registry, see
+    // `ComputeRegistry::register`.
+    pub fn register_function<F: 'static + Fn(&[&T]) -> T + Send + Sync>(&mut self, name: &str, compute_func: F) {
+        self.registry.register(name, compute_func)
+    }
+
+    // Creates a compute cell whose compute function is the one registered under the given name in
+    // the reactor's registry (see `with_registry` and `register_function`). The cell remembers the
+    // name (see `function_name`), and unlike other cells, it can be saved and loaded again, see
+    // `save`.
+    //
+    // Return an Err if any dependency doesn't exist, or if no function is registered under the name.
+    pub fn create_compute_named(&mut self, dependencies: &[CellID], name: &str) -> Result<CellID, ReactError> where T: 'static {
+        let compute_func = self.named_function(name)?;
+        let id = self.add_compute_cell(dependencies, compute_func, false)?;
+        self.computed_cell_mut(id.node).function_name = Some(name.to_string());
+        Ok(id)
+    }
+
+    // Creates a lazy compute cell from a registered function, see `create_compute_named` and
+    // `create_lazy_compute`.
+    pub fn create_lazy_compute_named(&mut self, dependencies: &[CellID], name: &str) -> Result<CellID, ReactError> where T: 'static {
+        let compute_func = self.named_function(name)?;
+        let id = self.add_compute_cell(dependencies, compute_func, true)?;
+        self.computed_cell_mut(id.node).function_name = Some(name.to_string());
+        Ok(id)
+    }
+
+    // Gets the name of the registered function the specified compute cell computes its value
+    // with, or None if the cell does not exist, or wasn't created from a registered function.
+    pub fn function_name(&self, id: CellID) -> Option<&str> {
+        match self.dep_graph[self.node(id).ok()?] {
+            Cell::Computed(ref computed) => computed.function_name.as_deref(),
+            Cell::Input(_) => None,
+        }
+    }
+
+    // Looks up a function of the reactor's registry, wrapped as the compute function of a cell.
+    fn named_function(&self, name: &str) -> Result<ComputeFunc<T>, ReactError> where T: 'static {
+        let compute_func = self.registry.get(name)
+            .cloned()
+            .ok_or_else(|| ReactError::UnknownFunction { name: name.to_string() })?;
+        Ok(Box::new(move |values| Ok(compute_func(values))))
+    }
+
+    // Creates a lazy compute cell, which is like a compute cell, except that changes to its
+    // dependencies only mark it as stale. It is then recomputed on demand, once its value is read
+    // through `value`, or is needed for recomputing a non-lazy cell that depends on it.
+    //
+    // Lazy cells that have callbacks can't defer their recomputation, and behave like non-lazy ones.
+    pub fn create_lazy_compute<F: 'static + Fn(&[&T]) -> T>(&mut self, dependencies: &[CellID], compute_func: F) -> Result<CellID, ReactError> {
+        self.add_compute_cell(dependencies, Box::new(move |values| Ok(compute_func(values))), true)
+    }
+
+    fn add_compute_cell(&mut self, dependencies: &[CellID], compute_func: ComputeFunc<T>, lazy: bool) -> Result<CellID, ReactError> {
+        self.check_dependencies(dependencies)?;
+        let generation = self.next_generation();
+        let origin = CellID { node: NodeIndex::end(), generation };
+        let value = dependencies.iter()
+            .map(|&id| self.current_value(id.node))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(Clone::clone)
+            .and_then(|values| compute_func(&values).map_err(|error| CellError::new(origin, error)));
+        let height = self.height_above(dependencies);
+        let computed = ComputedCell {
+            compute_func,
+            callbacks: HashMap::new(),
+            function_name: None,
+            comparator: None,
+            generation,
+            label: None,
+            height,
+            lazy,
+            stale: false,
+            fresh: OnceCell::new(),
+            value
+        };
+        let node = self.dep_graph.add_node(Cell::Computed(computed));
+        for (ix, &dep) in dependencies.iter().enumerate() {
+            self.dep_graph.add_edge(node, dep.node, ix);
+        }
+        // the node wasn't known when the compute function failed
+        if let Err(ref mut error) = self.computed_cell_mut(node).value {
+            if error.origin == origin {
+                error.origin.node = node;
+            }
+        }
+        let id = CellID { node, generation };
+        self.observer.observe(|observer| observer.compute_created(id, dependencies));
+        Ok(id)
+    }
+
+    // Replaces the dependencies and compute function of the specified compute cell, like editing
+    // the formula of a spreadsheet cell. The cell is immediately recomputed, and if its value
+    // changes, so are all cells depending on it, firing callbacks as `set_value` would.
+    //
+    // Return an Err if the cell or any dependency doesn't exist, if the cell is an input cell, or
+    // if any of the dependencies depends on the cell itself (or is the cell itself), as that would
+    // introduce a cycle.
+    pub fn set_dependencies<F: 'static + Fn(&[&T]) -> T>(&mut self, cell: CellID, dependencies: &[CellID], compute_func: F) -> Result<(), ReactError> {
+        self.rewire(cell, dependencies, Box::new(move |values| Ok(compute_func(values))), None)
+    }
+
+    // Replaces the dependencies of a compute cell, and its compute function with a registered
+    // one, see `set_dependencies` and `create_compute_named`.
+    pub fn set_dependencies_named(&mut self, cell: CellID, dependencies: &[CellID], name: &str) -> Result<(), ReactError> where T: 'static {
+        let compute_func = self.named_function(name)?;
+        self.rewire(cell, dependencies, compute_func, Some(name.to_string()))
+    }
+
+    fn rewire(&mut self, cell: CellID, dependencies: &[CellID], compute_func: ComputeFunc<T>, function_name: Option<String>) -> Result<(), ReactError> {
+        let node = self.node(cell)?;
+        if let Cell::Input(_) = self.dep_graph[node] {
+            return Err(ReactError::ExpectedComputedCell { id: cell, label: self.cell_label(node) })
+        }
+        self.check_dependencies(dependencies)?;
+        let dependants = self.find_deep_dependants(node);
+        let cyclic_deps = dependencies.iter()
+            .cloned()
+            .filter(|dep| dep.node == node || dependants.contains(&dep.node))
+            .collect::<Vec<_>>();
+        if !cyclic_deps.is_empty() {
+            return Err(ReactError::DependencyCycle {
+                id: cell,
+                label: self.cell_label(node),
+                labelled_cyclic_deps: self.cell_list(&cyclic_deps),
+                cyclic_deps,
+            })
+        }
+
+        let mut old_edges = Vec::new();
+        let mut dependency_walker = self.dep_graph.neighbors_directed(node, Direction::Outgoing).detach();
+        while let Some(edge) = dependency_walker.next_edge(&self.dep_graph) {
+            old_edges.push(edge);
+        }
+        for edge in old_edges {
+            self.dep_graph.remove_edge(edge);
+        }
+        for (ix, &dep) in dependencies.iter().enumerate() {
+            self.dep_graph.add_edge(node, dep.node, ix);
+        }
+        let height = self.height_above(dependencies);
+        let computed = self.computed_cell_mut(node);
+        computed.compute_func = compute_func;
+        computed.function_name = function_name;
+        self.set_height(node, height);
+        self.observer.observe(|observer| observer.dependencies_replaced(cell, dependencies));
+
+        self.observer.observe(|observer| observer.propagation_started(&[cell]));
+        self.trace.clear();
+        let changes = self.recompute(&[node])?;
+        self.notify(changes)
+    }
+
+    // Replaces how the specified compute cell decides whether its value has changed after being
+    // recomputed, which is by comparing the old and new values with `PartialEq` by default.
+    //
+    // The comparator is given the old and new values, and returns whether they're the same. Only
+    // a change lets the cell's callbacks fire and its dependants be recomputed; otherwise the cell
+    // keeps its old value. This allows for comparisons such as equality up to an epsilon, pointer
+    // equality, comparing hashes of large values, or considering every recomputation a change.
+    //
+    // Return an Err if the cell does not exist, or is an input cell.
+    pub fn set_comparator<F: 'static + Fn(&T, &T) -> bool>(&mut self, cell: CellID, comparator: F) -> Result<(), ReactError> {
+        let node = self.node(cell)?;
+        match self.dep_graph[node] {
+            Cell::Input(ref input) => Err(ReactError::ExpectedComputedCell { id: cell, label: CellLabel(input.label.clone()) }),
+            Cell::Computed(ref mut computed) => {
+                computed.comparator = Some(Box::new(comparator));
+                Ok(())
+            }
+        }
+    }
+
+    // Return an Err if any of the given dependencies doesn't exist.
+    fn check_dependencies(&self, dependencies: &[CellID]) -> Result<(), ReactError> {
         let missing_deps = dependencies.iter()
             .cloned()
-            .filter(|&dep| self.dep_graph.node_weight(dep).is_none())
+            .filter(|&dep| self.node(dep).is_err())
             .collect::<Vec<_>>();
-        if missing_deps.len() > 0 {
+        if !missing_deps.is_empty() {
             return Err(ReactError::MissingDepedencies {
                 missing_deps
             })
         }
-        let dependant_values = dependencies.iter()
-            .map(|&id| self.value(id).unwrap())
+        Ok(())
+    }
+
+    // Gets the height of a cell with the given dependencies.
+    fn height_above(&self, dependencies: &[CellID]) -> usize {
+        dependencies.iter()
+            .map(|&id| self.dep_graph[id.node].height() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Changes the height of a compute cell, raising the heights of the cells depending on it as
+    // needed to keep them above it. Heights are never lowered, as a cell that's too high is still
+    // recomputed after all of its dependencies.
+    fn set_height(&mut self, node: NodeIndex, height: usize) {
+        self.computed_cell_mut(node).height = height;
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            let min_height = self.dep_graph[node].height() + 1;
+            let mut dependants_walker = self.dep_graph.neighbors_directed(node, Direction::Incoming).detach();
+            while let Some(dep) = dependants_walker.next_node(&self.dep_graph) {
+                let cell = self.computed_cell_mut(dep);
+                if cell.height < min_height {
+                    cell.height = min_height;
+                    stack.push(dep);
+                }
+            }
+        }
+    }
+
+    // Removes the specified cell, along with its callbacks, returning the IDs of all removed cells.
+    //
+    // If other cells depend on it, the cell is either left in place with an Err, or removed along
+    // with all of the cells depending on it, according to the given `mode`.
+    //
+    // Return an Err if the cell does not exist.
+    pub fn remove_cell(&mut self, id: CellID, mode: RemovalMode) -> Result<Vec<CellID>, ReactError> {
+        let node = self.node(id)?;
+        let dependants = match mode {
+            RemovalMode::Restrict => {
+                let dependants = self.direct_dependants(id)?;
+                if !dependants.is_empty() {
+                    return Err(ReactError::CellInUse {
+                        id,
+                        label: self.cell_label(node),
+                        labelled_dependants: self.cell_list(&dependants),
+                        dependants,
+                    })
+                }
+                dependants
+            },
+            RemovalMode::Cascade => self.find_deep_dependants(node).into_iter()
+                .map(|dep| self.cell_id(dep))
+                .collect(),
+        };
+        let removed = Some(id).into_iter().chain(dependants).collect::<Vec<_>>();
+        for cell in &removed {
+            self.observer.observe(|observer| observer.cell_removed(*cell));
+            self.trace.remove(&cell.node);
+            if let Some(ref mut stats) = self.stats {
+                stats.remove(cell.node);
+            }
+            if let Some(label) = self.dep_graph.remove_node(cell.node).and_then(|cell| cell.label().map(String::from)) {
+                self.labels.remove(&label);
+            }
+        }
+        Ok(removed)
+    }
+
+    // Allocates a generation for a newly created cell.
+    fn next_generation(&mut self) -> u32 {
+        self.cur_generation += 1;
+        self.cur_generation - 1
+    }
+
+    // Resolves the graph node of the cell with the given ID, if it still exists.
+    fn node(&self, id: CellID) -> Result<NodeIndex, ReactError> {
+        match self.dep_graph.node_weight(id.node) {
+            Some(cell) if cell.generation() == id.generation => Ok(id.node),
+            _ if id.generation < self.cur_generation => Err(ReactError::RemovedCell { id }),
+            _ => Err(ReactError::MissingCell { id }),
+        }
+    }
+
+    // Labels the specified cell, so that it can be found through `cell_by_name`, and is told apart
+    // in error messages and debug output. Any label the cell had before is replaced.
+    //
+    // Return an Err if the cell does not exist, or if another cell already has the label.
+    pub fn set_label(&mut self, id: CellID, label: &str) -> Result<(), ReactError> {
+        let node = self.node(id)?;
+        match self.labels.get(label) {
+            Some(&other) if other != node => return Err(ReactError::DuplicateLabel {
+                label: label.to_string(),
+                id: self.cell_id(other),
+            }),
+            _ => {},
+        }
+        self.clear_label(id)?;
+        self.labels.insert(label.to_string(), node);
+        *self.dep_graph[node].label_mut() = Some(label.to_string());
+        Ok(())
+    }
+
+    // Removes the label of the specified cell, if it has one, making it available to other cells.
+    //
+    // Return an Err if the cell does not exist.
+    pub fn clear_label(&mut self, id: CellID) -> Result<(), ReactError> {
+        let node = self.node(id)?;
+        if let Some(label) = self.dep_graph[node].label_mut().take() {
+            self.labels.remove(&label);
+        }
+        Ok(())
+    }
+
+    // Gets the label of the specified cell, or None if the cell does not exist or has no label.
+    pub fn label(&self, id: CellID) -> Option<&str> {
+        self.dep_graph[self.node(id).ok()?].label()
+    }
+
+    // Finds the cell with the given label.
+    pub fn cell_by_name(&self, label: &str) -> Option<CellID> {
+        self.labels.get(label).map(|&node| self.cell_id(node))
+    }
+
+    // Gets the dependencies of the specified cell, in the order they're passed to its compute
+    // function. Input cells have no dependencies.
+    //
+    // Return an Err if the cell does not exist.
+    pub fn dependencies(&self, id: CellID) -> Result<Vec<CellID>, ReactError> {
+        let node = self.node(id)?;
+        Ok(self.dependency_nodes(node).into_iter().map(|dep| self.cell_id(dep)).collect())
+    }
+
+    // Gets the cells having the specified cell as one of their dependencies, ordered by ID.
+    //
+    // Return an Err if the cell does not exist.
+    pub fn direct_dependants(&self, id: CellID) -> Result<Vec<CellID>, ReactError> {
+        let node = self.node(id)?;
+        let mut dependants = self.dep_graph.neighbors_directed(node, Direction::Incoming)
+            .map(|dep| self.cell_id(dep))
             .collect::<Vec<_>>();
-        let value = compute_func(&dependant_values);
-        let computed = ComputedCell {
-            compute_func: Box::new(compute_func),
-            callbacks: HashMap::new(),
-            value
+        dependants.sort();
+        dependants.dedup();
+        Ok(dependants)
+    }
+
+    // Gets every cell depending on the specified cell, directly or indirectly, in the order they'd
+    // be recomputed in when the cell changes.
+    //
+    // Return an Err if the cell does not exist.
+    pub fn transitive_dependants(&self, id: CellID) -> Result<Vec<CellID>, ReactError> {
+        let node = self.node(id)?;
+        Ok(self.by_height(self.find_deep_dependants(node)))
+    }
+
+    // Gets the input cells the specified cell depends on, directly or indirectly, ordered by ID,
+    // which are the only cells whose changes may change the cell's value. An input cell's only
+    // upstream input is itself.
+    //
+    // Return an Err if the cell does not exist.
+    pub fn upstream_inputs(&self, id: CellID) -> Result<Vec<CellID>, ReactError> {
+        let node = self.node(id)?;
+        let mut inputs = Some(node).into_iter()
+            .chain(self.find_deep_dependencies(node))
+            .filter(|&dep| matches!(self.dep_graph[dep], Cell::Input(_)))
+            .map(|dep| self.cell_id(dep))
+            .collect::<Vec<_>>();
+        inputs.sort();
+        Ok(inputs)
+    }
+
+    // Tells whether the specified cell is an input cell, a compute cell or a lazy one.
+    //
+    // Return an Err if the cell does not exist.
+    pub fn cell_kind(&self, id: CellID) -> Result<CellKind, ReactError> {
+        Ok(match self.dep_graph[self.node(id)?] {
+            Cell::Input(_) => CellKind::Input,
+            Cell::Computed(ref computed) if computed.lazy => CellKind::LazyComputed,
+            Cell::Computed(_) => CellKind::Computed,
+        })
+    }
+
+    // Gets the IDs of the callbacks of the specified cell, in the order they were added.
+    //
+    // Return an Err if the cell does not exist.
+    pub fn callback_ids(&self, id: CellID) -> Result<Vec<CallbackID>, ReactError> {
+        let callbacks = match self.dep_graph[self.node(id)?] {
+            Cell::Input(InputCell { ref callbacks, .. }) |
+            Cell::Computed(ComputedCell { ref callbacks, .. }) => callbacks,
         };
-        let node = self.dep_graph.add_node(Cell::Computed(computed));
-        for (ix, &dep) in dependencies.iter().enumerate() {
-            self.dep_graph.add_edge(node, dep, ix);
+        let mut ids = callbacks.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        Ok(ids)
+    }
+
+    // Explains why the specified cell changed during the last propagation, by giving the path of
+    // changed cells leading to it, going from the cell itself back to the input cell that was set
+    // (or the compute cell whose dependencies were replaced, see `set_dependencies`). When several
+    // of a cell's dependencies changed, the path goes through the first of them in argument order.
+    //
+    // Lazy cells without callbacks aren't recomputed during propagation, so a path may go through
+    // one whose dependencies changed, even if its own value turns out not to have changed.
+    //
+    // The path is empty if the cell didn't change during the last propagation.
+    //
+    // Return an Err if the cell does not exist.
+    pub fn explain(&self, cell: CellID) -> Result<Vec<CellID>, ReactError> {
+        let mut node = self.node(cell)?;
+        let mut path = Vec::new();
+        while let Some(&cause) = self.trace.get(&node) {
+            path.push(self.cell_id(node));
+            match cause {
+                Some(cause) => node = cause,
+                None => break,
+            }
         }
-        Ok(node)
+        Ok(path)
+    }
+
+    // Sorts the given cells in increasing height order, giving their IDs.
+    fn by_height(&self, mut nodes: Vec<NodeIndex>) -> Vec<CellID> {
+        nodes.sort_by_key(|&node| (self.dep_graph[node].height(), node));
+        nodes.into_iter().map(|node| self.cell_id(node)).collect()
+    }
+
+    // Gets the label of the cell held by the given graph node, for error messages.
+    fn cell_label(&self, node: NodeIndex) -> CellLabel {
+        CellLabel(self.dep_graph[node].label().map(String::from))
+    }
+
+    // Gets the labels of the given existing cells, for error messages.
+    fn cell_list(&self, cells: &[CellID]) -> CellList {
+        CellList(cells.iter().map(|&id| (id, self.cell_label(id.node))).collect())
+    }
+
+    // Gets the ID of the cell held by the given graph node.
+    fn cell_id(&self, node: NodeIndex) -> CellID {
+        CellID { node, generation: self.dep_graph[node].generation() }
     }
 
     // Retrieves the current value of the cell, or None if the cell does not exist.
@@ -134,8 +876,71 @@ impl <'a, T: Copy + PartialEq> Reactor<'a, T> {
     //
     // It turns out this introduces a significant amount of extra complexity to this exercise.
     // We chose not to cover this here, since this exercise is probably enough work as-is.
+    //
+    // This is also None if the cell is in an error state, see `result` for telling these apart.
     pub fn value(&self, id: CellID) -> Option<T> {
-        self.dep_graph.node_weight(id).map(|cell| *cell.value())
+        self.node(id).ok().and_then(|node| self.current_value(node).ok().cloned())
+    }
+
+    // Retrieves the current value of the cell, or the error preventing it from being computed (see
+    // `create_fallible_compute`), or None if the cell does not exist.
+    pub fn result(&self, id: CellID) -> Option<Result<T, CellError>> {
+        self.node(id).ok().map(|node| self.current_value(node).cloned().map_err(Clone::clone))
+    }
+
+    // Gets the up to date value of the given cell, computing it if it's a stale lazy cell.
+    fn current_value(&self, node: NodeIndex) -> Result<&T, &CellError> {
+        if self.needs_refresh(node) {
+            self.refresh(node);
+        }
+        self.dep_graph[node].value()
+    }
+
+    // Whether the given cell is a stale lazy cell whose value wasn't computed on demand yet.
+    fn needs_refresh(&self, node: NodeIndex) -> bool {
+        match self.dep_graph[node] {
+            Cell::Computed(ref cell) => cell.stale && cell.fresh.get().is_none(),
+            Cell::Input(_) => false,
+        }
+    }
+
+    // Computes the value of a stale lazy cell on demand, along with the values of the stale lazy
+    // cells it depends on, directly or indirectly.
+    //
+    // These are computed in increasing height order, so that every dependency is up to date by
+    // the time it's needed, rather than recursing into it, which long chains of lazy cells could
+    // overflow the stack with.
+    fn refresh(&self, node: NodeIndex) {
+        let mut stale_cells = Vec::new();
+        let mut found = HashSet::new();
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            if self.needs_refresh(node) && found.insert(node) {
+                stale_cells.push(node);
+                stack.extend(self.dep_graph.neighbors_directed(node, Direction::Outgoing));
+            }
+        }
+        stale_cells.sort_by_key(|&node| self.dep_graph[node].height());
+        for node in stale_cells {
+            let cell = self.computed_cell(node);
+            cell.fresh.get_or_init(|| {
+                let values = self.dependency_values(node).map_err(Clone::clone)?;
+                let id = self.cell_id(node);
+                let (new_value, duration) = run_timed(&cell.compute_func, &values);
+                let new_value = new_value.map_err(|error| CellError::new(id, error));
+                self.observer.observe(|observer| observer.cell_recomputed(id, cell.value.as_ref(), new_value.as_ref(), duration));
+                let unchanged = cell.unchanged(&new_value);
+                if let Some(ref stats) = self.stats {
+                    stats.record_recompute(node, duration, unchanged);
+                }
+                // like eager cells, lazy ones keep their old value when it isn't considered changed
+                if unchanged {
+                    cell.value.clone()
+                } else {
+                    new_value
+                }
+            });
+        }
     }
 
     // Sets the value of the specified input cell.
@@ -148,111 +953,467 @@ impl <'a, T: Copy + PartialEq> Reactor<'a, T> {
     //
     // As before, that turned out to add too much extra complexity.
     pub fn set_value(&mut self, id: CellID, new_value: T) -> Result<(), ReactError> {
-        self.dep_graph.node_weight_mut(id).ok_or(ReactError::MissingCell { id}).and_then(|cell| match *cell {
-            Cell::Input(InputCell { ref mut value}) => {
-                *value = new_value;
-                Ok(())
-            },
-            Cell::Computed { .. } => Err(ReactError::ExpectedInputCell {
-                id
+        let step = self.write_inputs(vec![(id, new_value)])?;
+        self.record(step);
+        Ok(())
+    }
+
+    // Begins a transaction, which stages writes to any number of input cells and applies all of
+    // them at once when committed, as if they were a single `set_value` call.
+    pub fn transaction<'r>(&'r mut self) -> Transaction<'r, 'a, T> {
+        Transaction {
+            reactor: self,
+            staged: BTreeMap::new(),
+        }
+    }
+
+    // Captures the values of all input cells, which can be written back later through `restore`.
+    pub fn snapshot(&self) -> Snapshot<T> {
+        let values = self.dep_graph.node_indices()
+            .filter_map(|node| match self.dep_graph[node] {
+                Cell::Input(ref input) => Some((self.cell_id(node), input.value.clone())),
+                Cell::Computed(_) => None,
+            })
+            .collect();
+        Snapshot { values }
+    }
+
+    // Writes the values captured by a snapshot back into the input cells, propagating them in a
+    // single pass, as a transaction would. Input cells created after the snapshot was taken keep
+    // their values, and ones removed since are skipped.
+    pub fn restore(&mut self, snapshot: &Snapshot<T>) -> Result<(), ReactError> {
+        let writes = snapshot.values.iter()
+            .filter(|&&(id, _)| self.node(id).is_ok())
+            .cloned()
+            .collect();
+        let step = self.write_inputs(writes)?;
+        self.record(step);
+        Ok(())
+    }
+
+    // Enables undoing and redoing changes to input cells, remembering up to `depth` of the latest
+    // `set_value` calls and committed transactions. A depth of 0 disables the history, discarding
+    // it, which is the default.
+    //
+    // Only the values of input cells are part of the history: creating, removing or rewiring
+    // cells isn't undone, and undoing skips input cells that were removed since.
+    pub fn set_history_depth(&mut self, depth: usize) {
+        match self.history {
+            _ if depth == 0 => self.history = None,
+            Some(ref mut history) => history.set_depth(depth),
+            None => self.history = Some(History::new(depth)),
+        }
+    }
+
+    // Reverts the input cells changed by the latest `set_value` call or committed transaction that
+    // wasn't undone yet, propagating the change and firing callbacks like `set_value` would.
+    //
+    // Returns whether there was anything to undo.
+    pub fn undo(&mut self) -> Result<bool, ReactError> {
+        let step = match self.history.as_mut().and_then(History::pop_undo) {
+            Some(step) => step,
+            None => return Ok(false),
+        };
+        self.revisit(&step, |edit| edit.old_value.clone())?;
+        if let Some(ref mut history) = self.history {
+            history.push_redo(step);
+        }
+        Ok(true)
+    }
+
+    // Reapplies the latest change reverted by `undo`, unless other changes were made since.
+    //
+    // Returns whether there was anything to redo.
+    pub fn redo(&mut self) -> Result<bool, ReactError> {
+        let step = match self.history.as_mut().and_then(History::pop_redo) {
+            Some(step) => step,
+            None => return Ok(false),
+        };
+        self.revisit(&step, |edit| edit.new_value.clone())?;
+        if let Some(ref mut history) = self.history {
+            history.push_undo(step);
+        }
+        Ok(true)
+    }
+
+    // Whether there are changes that `undo` can revert.
+    pub fn can_undo(&self) -> bool {
+        self.history.as_ref().is_some_and(History::can_undo)
+    }
+
+    // Whether there are changes that `redo` can reapply.
+    pub fn can_redo(&self) -> bool {
+        self.history.as_ref().is_some_and(History::can_redo)
+    }
+
+    // Enables keeping statistics of how much work each cell takes, for finding the cells that are
+    // the most expensive to keep up to date, see `CellStats`. Disabling them discards them, and
+    // they're disabled by default.
+    pub fn set_stats_enabled(&mut self, enabled: bool) {
+        match self.stats {
+            _ if !enabled => self.stats = None,
+            Some(_) => {},
+            None => self.stats = Some(StatsTable::default()),
+        }
+    }
+
+    // Gets the statistics of the specified cell, or None if the cell does not exist or statistics
+    // aren't enabled.
+    pub fn cell_stats(&self, id: CellID) -> Option<CellStats> {
+        let node = self.node(id).ok()?;
+        self.stats.as_ref().map(|stats| stats.get(node))
+    }
+
+    // Lists the (up to) `count` cells whose compute functions took the longest in total, from the
+    // most expensive one, along with their statistics. Cells that were never recomputed aren't
+    // listed, nor is any cell if statistics aren't enabled.
+    pub fn most_expensive_cells(&self, count: usize) -> Vec<(CellID, CellStats)> {
+        let mut cells = self.stats.as_ref().map(StatsTable::all).unwrap_or_default();
+        cells.retain(|&(_, stats)| stats.recomputes > 0);
+        cells.sort_by_key(|&(node, stats)| (Reverse(stats.compute_time), Reverse(stats.recomputes), node));
+        cells.into_iter()
+            .take(count)
+            .map(|(node, stats)| (self.cell_id(node), stats))
+            .collect()
+    }
+
+    // Resets the statistics of all cells, if they're enabled.
+    pub fn reset_stats(&mut self) {
+        if let Some(ref mut stats) = self.stats {
+            stats.reset();
+        }
+    }
+
+    // Writes the values selected from a step of the history back into the input cells that still
+    // exist, without recording it as a new step.
+    fn revisit<F: Fn(&Edit<T>) -> T>(&mut self, step: &Step<T>, value: F) -> Result<(), ReactError> {
+        let writes = step.iter()
+            .filter(|edit| self.node(edit.id).is_ok())
+            .map(|edit| (edit.id, value(edit)))
+            .collect();
+        self.write_inputs(writes).map(|_| ())
+    }
+
+    // Writes new values into input cells and propagates them in a single pass, returning the
+    // writes that changed a value, if the history is enabled.
+    fn write_inputs(&mut self, writes: Vec<(CellID, T)>) -> Result<Step<T>, ReactError> {
+        // every cell is checked before any is written, so that a failed write leaves them all as
+        // they were, instead of leaving some of them written without being propagated.
+        for &(id, _) in &writes {
+            self.input_cell_mut(id)?;
+        }
+        let recording = self.history.is_some();
+        let mut step = Vec::new();
+        let mut inputs = Vec::new();
+        for (id, new_value) in writes {
+            let input = self.input_cell_mut(id)?;
+            if input.value == new_value {
+                continue;
+            }
+            if recording {
+                step.push(Edit { id, old_value: input.value.clone(), new_value: new_value.clone() });
+            }
+            inputs.push((id.node, std::mem::replace(&mut input.value, new_value)));
+        }
+        // writing the values the cells already hold doesn't start a propagation
+        if !inputs.is_empty() {
+            self.propagate(inputs)?;
+        }
+        Ok(step)
+    }
+
+    // Adds a step to the history, if it's enabled.
+    fn record(&mut self, step: Step<T>) {
+        if let Some(ref mut history) = self.history {
+            history.record(step);
+        }
+    }
+
+    // Gets the input cell with the given ID for modification.
+    fn input_cell_mut(&mut self, id: CellID) -> Result<&mut InputCell<'a, T>, ReactError> {
+        let node = self.node(id)?;
+        match self.dep_graph[node] {
+            Cell::Input(ref mut input) => Ok(input),
+            Cell::Computed(ref computed) => Err(ReactError::ExpectedInputCell {
+                id,
+                label: CellLabel(computed.label.clone()),
             })
-        })?;
-        let affected_cells = self.find_deep_dependencies_on(id);
-        let values_before_set = affected_cells.iter()
-            .map(|&dep| (dep, self.value(dep).unwrap()))
-            .collect::<HashMap<_,_>>();
-        self.update_dependants(id)?;
-        let values_after_set = affected_cells.iter()
-            .map(|&dep| (dep, self.value(dep).unwrap()))
-            .collect::<HashMap<_,_>>();
-        values_after_set.into_iter()
-            .filter(|&(node, new_value)| new_value != values_before_set[&node])
-            .map(|(node, _)| self.invoke_callback(node))
+        }
+    }
+
+    // Propagates changes from the given input cells, given along with their previous values, to
+    // all cells depending on them, then fires the callbacks of every cell whose value has changed.
+    fn propagate(&mut self, inputs: Vec<(NodeIndex, T)>) -> Result<(), ReactError> {
+        let nodes = inputs.iter().map(|&(node, _)| node).collect::<Vec<_>>();
+        let sources = nodes.iter().map(|&node| self.cell_id(node)).collect::<Vec<_>>();
+        self.observer.observe(|observer| observer.propagation_started(&sources));
+        self.trace = nodes.iter().map(|&node| (node, None)).collect();
+        let mut changes = inputs.into_iter()
+            .map(|(node, old_value)| (node, Ok(old_value)))
+            .collect::<Vec<_>>();
+        changes.extend(self.update_dependants(&nodes)?);
+        self.notify(changes)
+    }
+
+    // Fires the callbacks of the given changed cells, which ends a propagation.
+    fn notify(&mut self, changes: Vec<Change<T>>) -> Result<(), ReactError> {
+        let changed = changes.iter().map(|&(node, _)| self.cell_id(node)).collect::<Vec<_>>();
+        changes.into_iter()
+            .map(|(node, old_value)| self.invoke_callback(node, old_value.as_ref()))
             .collect::<Result<Vec<_>, _>>()?;
+        self.observer.observe(|observer| observer.propagation_finished(&changed));
         Ok(())
     }
 
 
-    // Updates a computed cell's value by applying the computation function on its
-    // dependencies, and also returns the updated value.
-    // If given cell is an input cell, it'll always return its input value.
-    fn compute_cell_shallow(&mut self, id: CellID) -> Result<T, ReactError> {
-        let mut dependency_values = BTreeMap::new();
-        let mut dependency_walker = self.dep_graph.neighbors_directed(id, Direction::Outgoing).detach();
-        while let Some((edge, node)) = dependency_walker.next(&self.dep_graph) {
+    // Recomputes a level of cells, which are all at the same height, returning the ones whose value
+    // has changed as a result.
+    fn compute_level(&mut self, level: &[NodeIndex]) -> Vec<Change<T>> {
+        // lazy cells without callbacks defer their recomputation until their value is needed, and
+        // as no callbacks are watching them, there's no need to know whether they changed either.
+        let (deferred, recomputed): (Vec<NodeIndex>, Vec<NodeIndex>) = level.iter()
+            .partition(|&&node| {
+                let cell = self.computed_cell(node);
+                cell.lazy && cell.callbacks.is_empty()
+            });
+        let new_values = {
+            let mut jobs = Vec::new();
+            let mut computed = Vec::new();
+            let mut failed = Vec::new();
+            // cells depending on a cell in an error state take on its error, without being computed
+            for node in recomputed {
+                match self.dependency_values(node) {
+                    Ok(values) => {
+                        jobs.push((&self.computed_cell(node).compute_func, values));
+                        computed.push(node);
+                    },
+                    Err(error) => failed.push((node, Err(error.clone()), None)),
+                }
+            }
+            (self.evaluate_level)(jobs).into_iter()
+                .zip(computed)
+                .map(|((new_value, duration), node)| {
+                    (node, new_value.map_err(|error| CellError::new(self.cell_id(node), error)), Some(duration))
+                })
+                .chain(failed)
+                .collect::<Vec<_>>()
+        };
+        for node in deferred {
+            let cell = self.computed_cell_mut(node);
+            cell.settle();
+            cell.stale = true;
+        }
+        let mut changes = Vec::new();
+        for (node, new_value, duration) in new_values {
+            self.computed_cell_mut(node).settle();
+            let unchanged = self.computed_cell(node).unchanged(&new_value);
+            if let Some(duration) = duration {
+                let id = self.cell_id(node);
+                let old_value = self.computed_cell(node).value.as_ref();
+                self.observer.observe(|observer| observer.cell_recomputed(id, old_value, new_value.as_ref(), duration));
+                if let Some(ref stats) = self.stats {
+                    stats.record_recompute(node, duration, unchanged);
+                }
+            }
+            if !unchanged {
+                let cell = self.computed_cell_mut(node);
+                changes.push((node, std::mem::replace(&mut cell.value, new_value)));
+            }
+        }
+        changes
+    }
+
+    // Gets the compute cell held by the given graph node, which must not hold an input cell.
+    fn computed_cell(&self, node: NodeIndex) -> &ComputedCell<'a, T> {
+        match self.dep_graph[node] {
+            Cell::Computed(ref cell) => cell,
+            Cell::Input(_) => unreachable!("input cells have no dependencies to be recomputed from"),
+        }
+    }
+
+    fn computed_cell_mut(&mut self, node: NodeIndex) -> &mut ComputedCell<'a, T> {
+        match self.dep_graph[node] {
+            Cell::Computed(ref mut cell) => cell,
+            Cell::Input(_) => unreachable!("input cells have no dependencies to be recomputed from"),
+        }
+    }
+
+    // Collects references to the values of a cell's dependencies, in argument order, or the error
+    // of the first dependency in an error state.
+    fn dependency_values(&self, node: NodeIndex) -> Result<Vec<&T>, &CellError> {
+        self.dependency_nodes(node).into_iter()
+            .map(|dep| self.current_value(dep))
+            .collect()
+    }
+
+    // Collects the dependencies of a cell, in argument order.
+    fn dependency_nodes(&self, node: NodeIndex) -> Vec<NodeIndex> {
+        let mut dependencies = BTreeMap::new();
+        let mut dependency_walker = self.dep_graph.neighbors_directed(node, Direction::Outgoing).detach();
+        while let Some((edge, dep)) = dependency_walker.next(&self.dep_graph) {
             let &ix = self.dep_graph.edge_weight(edge).unwrap();
-            let &val = self.dep_graph.node_weight(node).unwrap().value();
-            dependency_values.insert(ix, val);
-        }
-        let dependency_values = dependency_values.into_iter().map(|kvp| kvp.1).collect::<Vec<_>>();
-        self.dep_graph.node_weight_mut(id).ok_or(ReactError::MissingCell { id}).and_then(|cell| match cell {
-            &mut Cell::Input(InputCell { ref value }) => Ok(*value),
-            &mut Cell::Computed(ComputedCell { ref mut value, ref compute_func, .. }) => {
-                *value = compute_func(&dependency_values);
-                Ok(*value)
+            dependencies.insert(ix, dep);
+        }
+        dependencies.into_values().collect()
+    }
+
+    // Given cells whose values were changed, recomputes every cell that depends on them, directly
+    // or indirectly, returning the computed cells whose value has changed in the process.
+    fn update_dependants(&mut self, nodes: &[NodeIndex]) -> Result<Vec<Change<T>>, ReactError> {
+        let dependants = nodes.iter()
+            .flat_map(|&node| self.dep_graph.neighbors_directed(node, Direction::Incoming))
+            .collect::<Vec<_>>();
+        self.recompute(&dependants)
+    }
+
+    // Recomputes the given compute cells, along with every cell that depends on them, directly or
+    // indirectly, returning the computed cells whose value has changed in the process. Cells whose
+    // dependencies all kept their values aren't recomputed at all.
+    //
+    // Cells are recomputed in increasing height order, so by the time a cell is recomputed, all
+    // of its affected dependencies already hold their final values. This ensures each cell is
+    // recomputed exactly once, and that compute functions never observe intermediate states.
+    //
+    // As cells of the same height can't depend on one another, they're recomputed together as a
+    // level, which lets `evaluate_level` run their compute functions in parallel.
+    fn recompute(&mut self, nodes: &[NodeIndex]) -> Result<Vec<Change<T>>, ReactError> {
+        let mut changes = Vec::new();
+        let mut queue = BinaryHeap::new();
+        let mut queued = HashSet::new();
+        for &node in nodes {
+            if queued.insert(node) {
+                queue.push(Reverse((self.dep_graph[node].height(), node)));
             }
-        })
+        }
+        while let Some(Reverse((height, node))) = queue.pop() {
+            let mut level = vec![node];
+            while let Some(&Reverse((next_height, next_node))) = queue.peek() {
+                if next_height != height {
+                    break;
+                }
+                queue.pop();
+                level.push(next_node);
+            }
+            let level_changes = self.compute_level(&level);
+            // cells whose value didn't change shield their dependants from being recomputed, but
+            // deferred lazy cells may have changed, so their dependants must be checked as well.
+            let deferred = level.into_iter().filter(|&node| self.computed_cell(node).stale);
+            let touched = level_changes.iter().map(|&(node, _)| node).chain(deferred).collect::<Vec<_>>();
+            for node in touched {
+                let cause = self.dependency_nodes(node).into_iter().find(|dep| self.trace.contains_key(dep));
+                self.trace.insert(node, cause);
+                self.enqueue_dependants(node, &mut queue, &mut queued);
+            }
+            changes.extend(level_changes);
+        }
+        Ok(changes)
     }
 
-    // finds all computed cells that depend on the given cell, directly and indirectly.
-    fn find_deep_dependencies_on(&self, id: CellID) -> HashSet<CellID> {
-        let mut set = HashSet::new();
-        let mut walker = self.dep_graph.neighbors_directed(id, Direction::Incoming).detach();
-        while let Some(dep) = walker.next_node(&self.dep_graph) {
-            set.insert(dep);
-            set.extend(self.find_deep_dependencies_on(dep).iter());
+    // Pushes the cells that directly depend on the given cell into the propagation queue,
+    // unless they were already queued.
+    fn enqueue_dependants(&self, node: NodeIndex, queue: &mut BinaryHeap<Reverse<(usize, NodeIndex)>>, queued: &mut HashSet<NodeIndex>) {
+        for dep in self.dep_graph.neighbors_directed(node, Direction::Incoming) {
+            if queued.insert(dep) {
+                queue.push(Reverse((self.dep_graph[dep].height(), dep)));
+            }
         }
-        set
     }
 
-    // given a cell, recursively updates cells that depend on it
-    pub fn update_dependants(&mut self, id: CellID) -> Result<(), ReactError> {
-        // first, we update the cell itself
-        let cell_value = self.compute_cell_shallow(id)?;
-        // then, we update the cells that depend on it
-        let mut depends_on_walker = self.dep_graph.neighbors_directed(id, Direction::Incoming).detach();
-        while let Some(dep) = depends_on_walker.next_node(&self.dep_graph) {
-            self.update_dependants(dep)?;
+    // Finds all cells that depend on the given cell, directly or indirectly.
+    fn find_deep_dependants(&self, node: NodeIndex) -> Vec<NodeIndex> {
+        self.find_reachable(node, Direction::Incoming)
+    }
+
+    // Finds every cell the given cell depends on, directly or indirectly.
+    fn find_deep_dependencies(&self, node: NodeIndex) -> Vec<NodeIndex> {
+        self.find_reachable(node, Direction::Outgoing)
+    }
+
+    // Finds every cell reachable from the given cell by following edges in the given direction,
+    // excluding the cell itself.
+    fn find_reachable(&self, node: NodeIndex, direction: Direction) -> Vec<NodeIndex> {
+        let mut found = HashSet::new();
+        let mut reached = Vec::new();
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            for dep in self.dep_graph.neighbors_directed(node, direction) {
+                if found.insert(dep) {
+                    reached.push(dep);
+                    stack.push(dep);
+                }
+            }
         }
-        Ok(())
+        reached
     }
 
-    // Tries invoking the callbacks on a compute cell with the given ID.
-    fn invoke_callback(&mut self, id: CellID) -> Result<(), ReactError> {
-        self.dep_graph.node_weight_mut(id).ok_or(ReactError::MissingCell { id}).and_then(|val| match val {
-            &mut Cell::Input(_) => Err(ReactError::ExpectedComputedCell { id }),
-            &mut Cell::Computed(ComputedCell { ref value, ref mut callbacks, .. }) => {
-                callbacks.values_mut().for_each(|cb| cb(*value));
-                Ok(())
+    // Tries invoking the callbacks on the cell with the given ID, which changed from the given value.
+    fn invoke_callback(&mut self, node: NodeIndex, old_value: Result<&T, &CellError>) -> Result<(), ReactError> {
+        let id = self.cell_id(node);
+        let observer = &self.observer;
+        let stats = &self.stats;
+        self.dep_graph.node_weight_mut(node).ok_or(ReactError::MissingCell { id}).map(|val| {
+            let (new_value, callbacks) = match *val {
+                Cell::Input(InputCell { ref value, ref mut callbacks, .. }) => (Ok(value), callbacks),
+                Cell::Computed(ComputedCell { ref value, ref mut callbacks, .. }) => (value.as_ref(), callbacks),
+            };
+            for (&callback, cb) in callbacks.iter_mut() {
+                cb(id, old_value, new_value);
+                observer.observe(|observer| observer.callback_invoked(id, callback));
+                if let Some(ref stats) = *stats {
+                    stats.record_callback(node);
+                }
             }
         })
     }
 
-    // Adds a callback to the specified compute cell.
+    // Adds a callback to the specified cell.
     //
     // Return an Err (and you can change the error type) if the cell does not exist.
     //
-    // Callbacks on input cells will not be tested.
+    // Callbacks on input cells follow the same semantics, and are called along with those of
+    // compute cells once the new value has been propagated.
     //
     // The semantics of callbacks (as will be tested):
     // For a single set_value call, each compute cell's callbacks should each be called:
     // * Zero times if the compute cell's value did not change as a result of the set_value call.
     // * Exactly once if the compute cell's value changed as a result of the set_value call.
-    //   The value passed to the callback should be the final value of the compute cell after the
-    //   set_value call.
-    pub fn add_callback<F: FnMut(T) -> () + 'a>(&mut self, cell: CellID, callback: F) -> Result<CallbackID, ReactError> {
-        let mut id = &mut self.cur_callback_id;
-        self.dep_graph.node_weight_mut(cell).ok_or(ReactError::MissingCell { id: cell}).and_then(move |val| match *val {
-            Cell::Input(_) => Err(ReactError::ExpectedComputedCell { id: cell}),
-            Cell::Computed(ComputedCell { ref mut callbacks, ..}) => {
-                let cb = Box::new(callback);
-                callbacks.insert(*id, cb);
-                *id += 1;
-                Ok(*id - 1)
-            }
+    //   The value passed (by reference) to the callback should be the final value of the compute
+    //   cell after the set_value call.
+    //
+    // Callbacks aren't called when the cell enters an error state, only once it leaves it.
+    pub fn add_callback<F: FnMut(&T) + 'a>(&mut self, cell: CellID, mut callback: F) -> Result<CallbackID, ReactError> {
+        self.add_result_callback(cell, move |value| if let Ok(value) = value {
+            callback(value)
         })
     }
 
+    // Adds a callback to the specified cell, which is also called when the cell enters a
+    // different error state, with the new error.
+    pub fn add_result_callback<F: FnMut(Result<&T, &CellError>) + 'a>(&mut self, cell: CellID, mut callback: F) -> Result<CallbackID, ReactError> {
+        self.add_change_callback(cell, move |_, _, new_value| callback(new_value))
+    }
+
+    // Adds a callback to the specified cell, which is called with the cell's ID, its value before
+    // the set_value call (or transaction), and its new value. Either of them may be an error.
+    pub fn add_change_callback<F>(&mut self, cell: CellID, callback: F) -> Result<CallbackID, ReactError>
+        where F: FnMut(CellID, Result<&T, &CellError>, Result<&T, &CellError>) + 'a
+    {
+        let node = self.node(cell)?;
+        // a lazy cell with callbacks is kept up to date, so its changes can be detected.
+        let _ = self.current_value(node);
+        let id = &mut self.cur_callback_id;
+        let callbacks = match self.dep_graph[node] {
+            Cell::Input(ref mut input) => &mut input.callbacks,
+            Cell::Computed(ref mut computed) => {
+                computed.settle();
+                &mut computed.callbacks
+            }
+        };
+        callbacks.insert(*id, Box::new(callback));
+        *id += 1;
+        Ok(*id - 1)
+    }
+
     // Removes the specified callback, using an ID returned from add_callback.
     //
     // Return an Err (and you can change the error type) if either the cell or callback
@@ -260,16 +1421,63 @@ impl <'a, T: Copy + PartialEq> Reactor<'a, T> {
     //
     // A removed callback should no longer be called.
     pub fn remove_callback(&mut self, cell: CellID, callback: CallbackID) -> Result<(), ReactError> {
-        self.dep_graph.node_weight_mut(cell).ok_or(ReactError::MissingCell { id: cell}).and_then(|val| match *val {
-            Cell::Input(_) => Err(ReactError::ExpectedComputedCell { id: cell}),
-            Cell::Computed(ComputedCell { ref mut callbacks, ..}) => {
-                if !callbacks.contains_key(&callback) {
-                    Err(ReactError::CallbackDoesntExist { id: callback})
-                } else {
-                    callbacks.remove(&callback);
-                    Ok(())
-                }
-            }
-        })
+        let node = self.node(cell)?;
+        let callbacks = match self.dep_graph[node] {
+            Cell::Input(InputCell { ref mut callbacks, ..}) |
+            Cell::Computed(ComputedCell { ref mut callbacks, ..}) => callbacks,
+        };
+        if !callbacks.contains_key(&callback) {
+            Err(ReactError::CallbackDoesntExist { id: callback})
+        } else {
+            callbacks.remove(&callback);
+            Ok(())
+        }
     }
 }
+
+// A batch of input writes against a Reactor, created by `Reactor::transaction`.
+//
+// Writes are only staged, and don't affect the reactor until `commit` is called, at which point
+// they're all applied and propagated at once: every compute cell is recomputed at most once, and
+// its callbacks are fired at most once, with the value resulting from all of the writes.
+//
+// Dropping a transaction without committing it (or calling `rollback`) discards the staged writes.
+pub struct Transaction<'r, 'a: 'r, T: 'r> {
+    reactor: &'r mut Reactor<'a, T>,
+    // the staged writes, ordered by cell ID, so that committing them always fires callbacks and
+    // records the history in the same order.
+    staged: BTreeMap<CellID, T>,
+}
+
+impl <'r, 'a, T: Clone + PartialEq> Transaction<'r, 'a, T> {
+    // Stages a new value for the specified input cell, replacing any value staged for it before.
+    //
+    // Fails with the same errors as `Reactor::set_value`, in which case nothing is staged.
+    pub fn set_value(&mut self, id: CellID, new_value: T) -> Result<(), ReactError> {
+        self.reactor.input_cell_mut(id)?;
+        self.staged.insert(id, new_value);
+        Ok(())
+    }
+
+    // Retrieves the value of the cell as seen by this transaction: staged values of input cells
+    // are visible, but compute cells keep their values from before the transaction.
+    pub fn value(&self, id: CellID) -> Option<T> {
+        self.staged.get(&id).cloned().or_else(|| self.reactor.value(id))
+    }
+
+    // Applies all staged writes, propagating them in a single pass.
+    pub fn commit(self) -> Result<(), ReactError> {
+        let step = self.reactor.write_inputs(self.staged.into_iter().collect())?;
+        self.reactor.record(step);
+        Ok(())
+    }
+
+    // Discards all staged writes, leaving the reactor untouched.
+    pub fn rollback(self) {}
+}
+
+// The values of all input cells of a Reactor at some point, created by `Reactor::snapshot`.
+#[derive(Debug, Clone)]
+pub struct Snapshot<T> {
+    values: Vec<(CellID, T)>,
+}
@@ -0,0 +1,76 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::time::Duration;
+
+use {CallbackID, CellError, CellID};
+
+// Receives the events of a Reactor, for logging, collecting metrics or checking how cells are
+// recomputed, see `Reactor::set_observer`.
+//
+// Every method does nothing by default, so observers only implement the ones they need.
+pub trait ReactorObserver<T> {
+    // Called before propagating a change, with the input cells that were set, or the compute cell
+    // whose dependencies were replaced.
+    fn propagation_started(&mut self, _sources: &[CellID]) {}
+
+    // Called once a change was propagated and all callbacks were fired, with the cells whose
+    // values changed.
+    fn propagation_finished(&mut self, _changed: &[CellID]) {}
+
+    // Called whenever the compute function of a cell is run to recompute it, be it during
+    // propagation, or on demand for lazy cells, with the cell's old and new values (which may be
+    // the same) and how long the compute function took.
+    //
+    // Cells taking on the error of a dependency aren't recomputed, as their compute function isn't
+    // run, and neither are cells being created.
+    fn cell_recomputed(&mut self, _cell: CellID, _old_value: Result<&T, &CellError>, _new_value: Result<&T, &CellError>, _duration: Duration) {}
+
+    // Called after each callback of a cell is called.
+    fn callback_invoked(&mut self, _cell: CellID, _callback: CallbackID) {}
+
+    fn input_created(&mut self, _cell: CellID) {}
+
+    fn compute_created(&mut self, _cell: CellID, _dependencies: &[CellID]) {}
+
+    // Called when a compute cell's dependencies are replaced, before it's recomputed.
+    fn dependencies_replaced(&mut self, _cell: CellID, _dependencies: &[CellID]) {}
+
+    // Called for each cell being removed, including the ones removed along with another.
+    fn cell_removed(&mut self, _cell: CellID) {}
+}
+
+// Holds the observer of a Reactor, if it has one.
+//
+// Lazy cells are computed on demand while the reactor is only borrowed immutably, so the observer
+// needs to be borrowed mutably through a shared reference.
+pub struct ObserverSlot<'a, T> {
+    observer: RefCell<Option<Box<dyn ReactorObserver<T> + 'a>>>,
+}
+
+impl <'a, T> ObserverSlot<'a, T> {
+    pub fn new() -> Self {
+        ObserverSlot {
+            observer: RefCell::new(None),
+        }
+    }
+
+    pub fn replace(&mut self, observer: Option<Box<dyn ReactorObserver<T> + 'a>>) -> Option<Box<dyn ReactorObserver<T> + 'a>> {
+        std::mem::replace(self.observer.get_mut(), observer)
+    }
+
+    // Tells the observer about an event, if there's one.
+    pub fn observe<F: FnOnce(&mut (dyn ReactorObserver<T> + 'a))>(&self, event: F) {
+        if let Some(ref mut observer) = *self.observer.borrow_mut() {
+            event(&mut **observer);
+        }
+    }
+}
+
+impl <'a, T> fmt::Debug for ObserverSlot<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self.observer.borrow() {
+            Some(_) => write!(f, "Some(..)"),
+            None => write!(f, "None"),
+        }
+    }
+}
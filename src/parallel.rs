@@ -0,0 +1,27 @@
+use rayon::prelude::*;
+
+use {ComputeFunc, Job, Timed};
+
+// A compute function that's known to be `Sync`, despite being boxed as a plain `Fn`.
+struct SyncComputeFunc<'r, T: 'r>(&'r ComputeFunc<T>);
+
+// SAFETY: only constructed by `evaluate_in_parallel`, which is only ever used by reactors created
+// through `SendReactor::new_parallel`, and those only accept compute functions that are `Sync`.
+unsafe impl <'r, T> Send for SyncComputeFunc<'r, T> {}
+unsafe impl <'r, T> Sync for SyncComputeFunc<'r, T> {}
+
+impl <'r, T> SyncComputeFunc<'r, T> {
+    fn call(&self, values: &[&T]) -> Timed<T> {
+        ::run_timed(self.0, values)
+    }
+}
+
+// Runs the jobs of a propagation level on rayon's thread pool.
+pub fn evaluate_in_parallel<T: Send + Sync>(jobs: Vec<Job<T>>) -> Vec<Timed<T>> {
+    jobs.into_iter()
+        .map(|(compute_func, values)| (SyncComputeFunc(compute_func), values))
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|(compute_func, values)| compute_func.call(&values))
+        .collect()
+}
@@ -0,0 +1,175 @@
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet};
+use petgraph::graph::NodeIndex;
+use serde::{Deserialize, Serialize};
+
+use {Cell, CellError, CellID, ComputeFunc, ComputeRegistry, ComputedCell, InputCell, ReactError, Reactor};
+
+// The structure of a Reactor along with the values of its input cells, which can be serialized
+// with serde, created by `Reactor::save`.
+//
+// Cells are saved along with their IDs and labels, so the IDs of a reactor's cells remain valid
+// once it's loaded again. Callbacks, comparators and the undo history aren't saved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedReactor<T> {
+    // the cells, saved in increasing height order, so each cell comes after its dependencies.
+    cells: Vec<SavedCell<T>>,
+    // the generation of the next cell to be created.
+    next_generation: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SavedCell<T> {
+    index: usize,
+    generation: u32,
+    #[serde(default)]
+    label: Option<String>,
+    kind: SavedCellKind<T>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum SavedCellKind<T> {
+    Input { value: T },
+    // the dependencies are the indices of the dependency cells, in argument order.
+    Computed { function: String, dependencies: Vec<usize>, lazy: bool },
+}
+
+impl <'a, T: Clone + PartialEq> Reactor<'a, T> {
+    // Saves the structure of the reactor, along with the values of its input cells, so that it can
+    // be serialized, and loaded again through `load`.
+    //
+    // Return an Err if any compute cell wasn't created from a named function (see
+    // `create_compute_named`), as there'd be no way to restore its compute function.
+    pub fn save(&self) -> Result<SavedReactor<T>, ReactError> {
+        let mut nodes = self.dep_graph.node_indices().collect::<Vec<_>>();
+        nodes.sort_by_key(|&node| self.dep_graph[node].height());
+        let cells = nodes.into_iter()
+            .map(|node| {
+                let kind = match self.dep_graph[node] {
+                    Cell::Input(ref input) => SavedCellKind::Input { value: input.value.clone() },
+                    Cell::Computed(ref computed) => SavedCellKind::Computed {
+                        function: computed.function_name.clone()
+                            .ok_or_else(|| ReactError::UnnamedFunction { id: self.cell_id(node), label: self.cell_label(node) })?,
+                        dependencies: self.dependency_nodes(node).into_iter().map(|dep| dep.index()).collect(),
+                        lazy: computed.lazy,
+                    },
+                };
+                let cell = &self.dep_graph[node];
+                Ok(SavedCell { index: node.index(), generation: cell.generation(), label: cell.label().map(String::from), kind })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(SavedReactor { cells, next_generation: self.cur_generation })
+    }
+
+    // Loads a reactor saved by `save`, taking the compute functions of its cells from the given
+    // registry, which the loaded reactor keeps for creating new cells (see `with_registry`).
+    //
+    // The values of all compute cells are computed anew, lazy ones included.
+    //
+    // Return an Err if a cell's function isn't registered, or if the saved reactor is malformed.
+    pub fn load(saved: SavedReactor<T>, registry: ComputeRegistry<T>) -> Result<Self, ReactError> where T: 'static {
+        let mut reactor = Reactor::with_registry(registry);
+        let mut slots = HashMap::new();
+        for cell in &saved.cells {
+            if slots.insert(cell.index, cell).is_some() {
+                return Err(invalid(format!("there are several cells at index {}", cell.index)));
+            }
+            // a cell can only be at an index some cell was created at, which also keeps the index
+            // within the range of node indices.
+            if cell.index >= saved.next_generation as usize {
+                return Err(invalid(format!("no cell was ever created at index {}", cell.index)));
+            }
+            if cell.generation >= saved.next_generation {
+                return Err(invalid(format!("the cell at index {} is newer than the reactor", cell.index)));
+            }
+            if let Some(ref label) = cell.label {
+                if reactor.labels.insert(label.clone(), NodeIndex::new(cell.index)).is_some() {
+                    return Err(invalid(format!("there are several cells labelled {:?}", label)));
+                }
+            }
+        }
+
+        // cells are placed at their original indices, so that their IDs remain valid, and the
+        // indices of removed cells are filled with placeholders until all cells are placed.
+        let len = slots.keys().map(|&index| index + 1).max().unwrap_or(0);
+        let mut placeholders = Vec::new();
+        for index in 0..len {
+            let cell = match slots.get(&index) {
+                Some(&&SavedCell { generation, ref label, kind: SavedCellKind::Input { ref value }, .. }) =>
+                    Cell::Input(InputCell { value: value.clone(), generation, label: label.clone(), callbacks: HashMap::new() }),
+                Some(&&SavedCell { generation, ref label, kind: SavedCellKind::Computed { ref function, lazy, .. }, .. }) => {
+                    let compute_func = reactor.registry.get(function)
+                        .cloned()
+                        .ok_or_else(|| ReactError::UnknownFunction { name: function.clone() })?;
+                    let mut computed = unloaded_cell(generation, Box::new(move |values| Ok(compute_func(values))));
+                    computed.function_name = Some(function.clone());
+                    computed.label = label.clone();
+                    computed.lazy = lazy;
+                    Cell::Computed(computed)
+                },
+                None => {
+                    placeholders.push(NodeIndex::new(index));
+                    Cell::Computed(unloaded_cell(0, Box::new(|_| Err(format_err!("placeholder")))))
+                },
+            };
+            reactor.dep_graph.add_node(cell);
+        }
+
+        let mut loaded = HashSet::new();
+        for cell in &saved.cells {
+            let node = NodeIndex::new(cell.index);
+            if let SavedCellKind::Computed { ref dependencies, .. } = cell.kind {
+                if let Some(&dep) = dependencies.iter().find(|dep| !loaded.contains(*dep)) {
+                    return Err(invalid(format!("the cell at index {} depends on a cell at index {} coming after it", cell.index, dep)));
+                }
+                for (ix, &dep) in dependencies.iter().enumerate() {
+                    reactor.dep_graph.add_edge(node, NodeIndex::new(dep), ix);
+                }
+                let dependencies = dependencies.iter()
+                    .map(|&dep| reactor.cell_id(NodeIndex::new(dep)))
+                    .collect::<Vec<_>>();
+                reactor.computed_cell_mut(node).height = reactor.height_above(&dependencies);
+            }
+            loaded.insert(cell.index);
+        }
+        for node in placeholders {
+            reactor.dep_graph.remove_node(node);
+        }
+        reactor.cur_generation = saved.next_generation;
+
+        // every compute cell starts out stale, so computing their values on demand also computes
+        // the values of their dependencies first.
+        let computed_nodes = reactor.dep_graph.node_indices()
+            .filter(|&node| reactor.needs_refresh(node))
+            .collect::<Vec<_>>();
+        for &node in &computed_nodes {
+            reactor.refresh(node);
+        }
+        for node in computed_nodes {
+            reactor.computed_cell_mut(node).settle();
+        }
+        Ok(reactor)
+    }
+}
+
+// Creates a stale compute cell, which has no value until it's computed on demand.
+fn unloaded_cell<'a, T>(generation: u32, compute_func: ComputeFunc<T>) -> ComputedCell<'a, T> {
+    let origin = CellID { node: NodeIndex::end(), generation };
+    ComputedCell {
+        value: Err(CellError::new(origin, format_err!("the cell wasn't loaded yet"))),
+        generation,
+        label: None,
+        height: 0,
+        compute_func,
+        callbacks: HashMap::new(),
+        function_name: None,
+        comparator: None,
+        lazy: false,
+        stale: true,
+        fresh: OnceCell::new(),
+    }
+}
+
+fn invalid(reason: String) -> ReactError {
+    ReactError::InvalidSave { reason }
+}
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+// A compute function registered under a name, which can be shared by any number of cells.
+pub type NamedComputeFunc<T> = Arc<dyn Fn(&[&T]) -> T + Send + Sync>;
+
+// Compute functions registered under names, for creating compute cells that remember which
+// function they compute their value with, see `Reactor::create_compute_named`.
+//
+// Registered functions must be `Send` and `Sync`, so that a registry can be used by any reactor,
+// including a `SendReactor`.
+pub struct ComputeRegistry<T> {
+    functions: HashMap<String, NamedComputeFunc<T>>,
+}
+
+impl <T> Default for ComputeRegistry<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl <T> ComputeRegistry<T> {
+    pub fn new() -> Self {
+        ComputeRegistry {
+            functions: HashMap::new(),
+        }
+    }
+
+    // Registers a compute function under the given name, replacing any function registered under
+    // it before. Cells created with the old function keep using it.
+    pub fn register<F: 'static + Fn(&[&T]) -> T + Send + Sync>(&mut self, name: &str, compute_func: F) {
+        self.functions.insert(name.to_string(), Arc::new(compute_func));
+    }
+
+    // Gets the compute function registered under the given name.
+    pub fn get(&self, name: &str) -> Option<&NamedComputeFunc<T>> {
+        self.functions.get(name)
+    }
+}
+
+impl <T> fmt::Debug for ComputeRegistry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.functions.keys()).finish()
+    }
+}
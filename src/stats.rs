@@ -0,0 +1,59 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Duration;
+use petgraph::graph::NodeIndex;
+
+// How much work a cell took since statistics were enabled or last reset, see
+// `Reactor::set_stats_enabled`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CellStats {
+    // how many times the cell's compute function was run, not counting its creation.
+    pub recomputes: u64,
+    // how many of those recomputations left the cell's value unchanged, which were wasted work.
+    pub unchanged_recomputes: u64,
+    // how long the cell's compute function took, summed over all recomputations.
+    pub compute_time: Duration,
+    // how many times the callbacks of the cell were called, counting each callback separately.
+    pub callback_calls: u64,
+}
+
+// The statistics of the cells of a Reactor.
+//
+// Like the observer, these are updated while lazy cells are computed on demand, when the reactor
+// is only borrowed immutably.
+#[derive(Debug, Default)]
+pub struct StatsTable {
+    cells: RefCell<HashMap<NodeIndex, CellStats>>,
+}
+
+impl StatsTable {
+    pub fn record_recompute(&self, node: NodeIndex, duration: Duration, unchanged: bool) {
+        let mut cells = self.cells.borrow_mut();
+        let stats = cells.entry(node).or_default();
+        stats.recomputes += 1;
+        stats.compute_time += duration;
+        if unchanged {
+            stats.unchanged_recomputes += 1;
+        }
+    }
+
+    pub fn record_callback(&self, node: NodeIndex) {
+        self.cells.borrow_mut().entry(node).or_default().callback_calls += 1;
+    }
+
+    pub fn get(&self, node: NodeIndex) -> CellStats {
+        self.cells.borrow().get(&node).cloned().unwrap_or_default()
+    }
+
+    pub fn all(&self) -> Vec<(NodeIndex, CellStats)> {
+        self.cells.borrow().iter().map(|(&node, &stats)| (node, stats)).collect()
+    }
+
+    pub fn remove(&mut self, node: NodeIndex) {
+        self.cells.get_mut().remove(&node);
+    }
+
+    pub fn reset(&mut self) {
+        self.cells.get_mut().clear();
+    }
+}
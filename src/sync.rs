@@ -0,0 +1,357 @@
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use failure;
+
+#[cfg(feature = "serde")]
+use SavedReactor;
+use {CallbackID, CellError, CellID, CellKind, CellStats, ComputeRegistry, ReactError, Reactor, ReactorObserver, RemovalMode, Snapshot, Transaction};
+
+// A Reactor that can be moved across threads.
+//
+// A plain Reactor accepts compute functions and callbacks that aren't `Send`, such as ones
+// capturing an `Rc`, and so can't be `Send` itself. This variant only accepts ones that are.
+// Compute functions must also be `Sync`, so that they can be run in parallel (see `new_parallel`).
+pub struct SendReactor<'a, T> {
+    reactor: Reactor<'a, T>,
+}
+
+// SAFETY: the compute functions, callbacks and observer are the only parts of a Reactor that may
+// not be `Send` when `T` is, and a SendReactor never lets ones that aren't `Send` into its reactor.
+unsafe impl <'a, T: Send> Send for SendReactor<'a, T> {}
+
+impl <'a, T: Clone + PartialEq + Send> Default for SendReactor<'a, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl <'a, T: Clone + PartialEq + Send> SendReactor<'a, T> {
+    pub fn new() -> Self {
+        SendReactor {
+            reactor: Reactor::new(),
+        }
+    }
+
+    // Creates a reactor which recomputes the cells of each propagation level in parallel, on
+    // rayon's global thread pool. Callbacks are still fired on the thread that changed the inputs,
+    // once all cells have been recomputed.
+    #[cfg(feature = "parallel")]
+    pub fn new_parallel() -> Self where T: Sync {
+        let mut reactor = Reactor::new();
+        reactor.evaluate_level = ::parallel::evaluate_in_parallel;
+        SendReactor {
+            reactor,
+        }
+    }
+
+    // Creates a reactor with the given registry of compute functions, see `Reactor::with_registry`.
+    pub fn with_registry(registry: ComputeRegistry<T>) -> Self {
+        SendReactor {
+            reactor: Reactor::with_registry(registry),
+        }
+    }
+
+    // Loads a reactor saved by `save`, see `Reactor::load`.
+    //
+    // Every compute function of the loaded reactor comes from the registry, whose functions are
+    // all `Send` and `Sync` (see `NamedComputeFunc`), so the reactor is safe to move across threads.
+    #[cfg(feature = "serde")]
+    pub fn load(saved: SavedReactor<T>, registry: ComputeRegistry<T>) -> Result<Self, ReactError> where T: 'static {
+        Ok(SendReactor {
+            reactor: Reactor::load(saved, registry)?,
+        })
+    }
+
+    // Saves the structure of the reactor along with its input values, see `Reactor::save`.
+    #[cfg(feature = "serde")]
+    pub fn save(&self) -> Result<SavedReactor<T>, ReactError> {
+        self.reactor.save()
+    }
+
+    // Registers a named compute function, see `Reactor::register_function`.
+    pub fn register_function<F: 'static + Fn(&[&T]) -> T + Send + Sync>(&mut self, name: &str, compute_func: F) {
+        self.reactor.register_function(name, compute_func)
+    }
+
+    // Sets the observer of the reactor, see `Reactor::set_observer`.
+    pub fn set_observer(&mut self, observer: Box<dyn ReactorObserver<T> + Send + 'a>) {
+        self.reactor.set_observer(observer)
+    }
+
+    // Removes the observer of the reactor, giving it back, see `Reactor::take_observer`.
+    pub fn take_observer(&mut self) -> Option<Box<dyn ReactorObserver<T> + 'a>> {
+        self.reactor.take_observer()
+    }
+
+    // Creates an input cell, see `Reactor::create_input`.
+    pub fn create_input(&mut self, initial: T) -> CellID {
+        self.reactor.create_input(initial)
+    }
+
+    // Creates a compute cell, see `Reactor::create_compute`.
+    pub fn create_compute<F: 'static + Fn(&[&T]) -> T + Send + Sync>(&mut self, dependencies: &[CellID], compute_func: F) -> Result<CellID, ReactError> {
+        self.reactor.create_compute(dependencies, compute_func)
+    }
+
+    // Creates a compute cell whose compute function may fail, see `Reactor::create_fallible_compute`.
+    pub fn create_fallible_compute<F, E>(&mut self, dependencies: &[CellID], compute_func: F) -> Result<CellID, ReactError>
+        where F: 'static + Fn(&[&T]) -> Result<T, E> + Send + Sync, E: Into<failure::Error>
+    {
+        self.reactor.create_fallible_compute(dependencies, compute_func)
+    }
+
+    // Creates a compute cell from a registered function, see `Reactor::create_compute_named`.
+    pub fn create_compute_named(&mut self, dependencies: &[CellID], name: &str) -> Result<CellID, ReactError> where T: 'static {
+        self.reactor.create_compute_named(dependencies, name)
+    }
+
+    // Creates a lazy compute cell from a registered function, see `Reactor::create_lazy_compute_named`.
+    pub fn create_lazy_compute_named(&mut self, dependencies: &[CellID], name: &str) -> Result<CellID, ReactError> where T: 'static {
+        self.reactor.create_lazy_compute_named(dependencies, name)
+    }
+
+    // Gets the name of the function of a compute cell, see `Reactor::function_name`.
+    pub fn function_name(&self, id: CellID) -> Option<&str> {
+        self.reactor.function_name(id)
+    }
+
+    // Creates a lazy compute cell, see `Reactor::create_lazy_compute`.
+    pub fn create_lazy_compute<F: 'static + Fn(&[&T]) -> T + Send + Sync>(&mut self, dependencies: &[CellID], compute_func: F) -> Result<CellID, ReactError> {
+        self.reactor.create_lazy_compute(dependencies, compute_func)
+    }
+
+    // Replaces the dependencies and compute function of a compute cell, see `Reactor::set_dependencies`.
+    pub fn set_dependencies<F: 'static + Fn(&[&T]) -> T + Send + Sync>(&mut self, cell: CellID, dependencies: &[CellID], compute_func: F) -> Result<(), ReactError> {
+        self.reactor.set_dependencies(cell, dependencies, compute_func)
+    }
+
+    // Replaces the dependencies of a compute cell, and its compute function with a registered one,
+    // see `Reactor::set_dependencies_named`.
+    pub fn set_dependencies_named(&mut self, cell: CellID, dependencies: &[CellID], name: &str) -> Result<(), ReactError> where T: 'static {
+        self.reactor.set_dependencies_named(cell, dependencies, name)
+    }
+
+    // Replaces how a compute cell detects changes, see `Reactor::set_comparator`.
+    pub fn set_comparator<F: 'static + Fn(&T, &T) -> bool + Send>(&mut self, cell: CellID, comparator: F) -> Result<(), ReactError> {
+        self.reactor.set_comparator(cell, comparator)
+    }
+
+    // Retrieves the current value of the cell, see `Reactor::value`.
+    pub fn value(&self, id: CellID) -> Option<T> {
+        self.reactor.value(id)
+    }
+
+    // Retrieves the current value of the cell or its error, see `Reactor::result`.
+    pub fn result(&self, id: CellID) -> Option<Result<T, CellError>> {
+        self.reactor.result(id)
+    }
+
+    // Sets the value of the specified input cell, see `Reactor::set_value`.
+    pub fn set_value(&mut self, id: CellID, new_value: T) -> Result<(), ReactError> {
+        self.reactor.set_value(id, new_value)
+    }
+
+    // Captures the values of all input cells, see `Reactor::snapshot`.
+    pub fn snapshot(&self) -> Snapshot<T> {
+        self.reactor.snapshot()
+    }
+
+    // Writes the values captured by a snapshot back, see `Reactor::restore`.
+    pub fn restore(&mut self, snapshot: &Snapshot<T>) -> Result<(), ReactError> {
+        self.reactor.restore(snapshot)
+    }
+
+    // Enables undoing changes to input cells, see `Reactor::set_history_depth`.
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.reactor.set_history_depth(depth)
+    }
+
+    // Reverts the latest change to input cells, see `Reactor::undo`.
+    pub fn undo(&mut self) -> Result<bool, ReactError> {
+        self.reactor.undo()
+    }
+
+    // Reapplies the latest undone change, see `Reactor::redo`.
+    pub fn redo(&mut self) -> Result<bool, ReactError> {
+        self.reactor.redo()
+    }
+
+    // Tells whether there's a change to undo, see `Reactor::can_undo`.
+    pub fn can_undo(&self) -> bool {
+        self.reactor.can_undo()
+    }
+
+    // Tells whether there's an undone change to redo, see `Reactor::can_redo`.
+    pub fn can_redo(&self) -> bool {
+        self.reactor.can_redo()
+    }
+
+    // Enables or disables statistics of the work each cell takes, see `Reactor::set_stats_enabled`.
+    pub fn set_stats_enabled(&mut self, enabled: bool) {
+        self.reactor.set_stats_enabled(enabled)
+    }
+
+    // Gets the statistics of the specified cell, see `Reactor::cell_stats`.
+    pub fn cell_stats(&self, id: CellID) -> Option<CellStats> {
+        self.reactor.cell_stats(id)
+    }
+
+    // Lists the most expensive cells, see `Reactor::most_expensive_cells`.
+    pub fn most_expensive_cells(&self, count: usize) -> Vec<(CellID, CellStats)> {
+        self.reactor.most_expensive_cells(count)
+    }
+
+    // Resets the statistics of all cells, see `Reactor::reset_stats`.
+    pub fn reset_stats(&mut self) {
+        self.reactor.reset_stats()
+    }
+
+    // Begins a transaction, see `Reactor::transaction`.
+    pub fn transaction<'r>(&'r mut self) -> Transaction<'r, 'a, T> {
+        self.reactor.transaction()
+    }
+
+    // Labels the specified cell, see `Reactor::set_label`.
+    pub fn set_label(&mut self, id: CellID, label: &str) -> Result<(), ReactError> {
+        self.reactor.set_label(id, label)
+    }
+
+    // Finds the cell with the given label, see `Reactor::cell_by_name`.
+    pub fn cell_by_name(&self, label: &str) -> Option<CellID> {
+        self.reactor.cell_by_name(label)
+    }
+
+    // Removes the label of the specified cell, see `Reactor::clear_label`.
+    pub fn clear_label(&mut self, id: CellID) -> Result<(), ReactError> {
+        self.reactor.clear_label(id)
+    }
+
+    // Gets the label of the specified cell, see `Reactor::label`.
+    pub fn label(&self, id: CellID) -> Option<&str> {
+        self.reactor.label(id)
+    }
+
+    // Gets the dependencies of the specified cell, see `Reactor::dependencies`.
+    pub fn dependencies(&self, id: CellID) -> Result<Vec<CellID>, ReactError> {
+        self.reactor.dependencies(id)
+    }
+
+    // Gets the cells directly depending on the specified cell, see `Reactor::direct_dependants`.
+    pub fn direct_dependants(&self, id: CellID) -> Result<Vec<CellID>, ReactError> {
+        self.reactor.direct_dependants(id)
+    }
+
+    // Gets every cell depending on the specified cell, see `Reactor::transitive_dependants`.
+    pub fn transitive_dependants(&self, id: CellID) -> Result<Vec<CellID>, ReactError> {
+        self.reactor.transitive_dependants(id)
+    }
+
+    // Gets the input cells the specified cell depends on, see `Reactor::upstream_inputs`.
+    pub fn upstream_inputs(&self, id: CellID) -> Result<Vec<CellID>, ReactError> {
+        self.reactor.upstream_inputs(id)
+    }
+
+    // Gets the kind of the specified cell, see `Reactor::cell_kind`.
+    pub fn cell_kind(&self, id: CellID) -> Result<CellKind, ReactError> {
+        self.reactor.cell_kind(id)
+    }
+
+    // Gets the IDs of the callbacks of the specified cell, see `Reactor::callback_ids`.
+    pub fn callback_ids(&self, id: CellID) -> Result<Vec<CallbackID>, ReactError> {
+        self.reactor.callback_ids(id)
+    }
+
+    // Explains why the specified cell changed, see `Reactor::explain`.
+    pub fn explain(&self, cell: CellID) -> Result<Vec<CellID>, ReactError> {
+        self.reactor.explain(cell)
+    }
+
+    // Removes the specified cell, see `Reactor::remove_cell`.
+    pub fn remove_cell(&mut self, id: CellID, mode: RemovalMode) -> Result<Vec<CellID>, ReactError> {
+        self.reactor.remove_cell(id, mode)
+    }
+
+    // Adds a callback to the specified cell, see `Reactor::add_callback`.
+    pub fn add_callback<F: FnMut(&T) + Send + 'a>(&mut self, cell: CellID, callback: F) -> Result<CallbackID, ReactError> {
+        self.reactor.add_callback(cell, callback)
+    }
+
+    // Adds a callback that's also told about errors, see `Reactor::add_result_callback`.
+    pub fn add_result_callback<F: FnMut(Result<&T, &CellError>) + Send + 'a>(&mut self, cell: CellID, callback: F) -> Result<CallbackID, ReactError> {
+        self.reactor.add_result_callback(cell, callback)
+    }
+
+    // Adds a callback that's also given the previous value, see `Reactor::add_change_callback`.
+    pub fn add_change_callback<F>(&mut self, cell: CellID, callback: F) -> Result<CallbackID, ReactError>
+        where F: FnMut(CellID, Result<&T, &CellError>, Result<&T, &CellError>) + Send + 'a
+    {
+        self.reactor.add_change_callback(cell, callback)
+    }
+
+    // Removes the specified callback, see `Reactor::remove_callback`.
+    pub fn remove_callback(&mut self, cell: CellID, callback: CallbackID) -> Result<(), ReactError> {
+        self.reactor.remove_callback(cell, callback)
+    }
+
+    // Renders the cells of the reactor as a Graphviz DOT digraph, see `Reactor::to_dot`.
+    pub fn to_dot(&self) -> String where T: fmt::Debug {
+        self.reactor.to_dot()
+    }
+}
+
+// A handle to a reactor shared between threads. Cloning the handle shares the same reactor.
+//
+// Every operation locks the reactor for its whole duration, so propagations are serialized: each
+// `set_value` (or transaction) propagates and fires its callbacks completely before the next one
+// starts, and no thread ever observes the values of a propagation in progress.
+pub struct SharedReactor<'a, T> {
+    reactor: Arc<Mutex<SendReactor<'a, T>>>,
+}
+
+impl <'a, T> Clone for SharedReactor<'a, T> {
+    fn clone(&self) -> Self {
+        SharedReactor {
+            reactor: self.reactor.clone(),
+        }
+    }
+}
+
+impl <'a, T: Clone + PartialEq + Send> Default for SharedReactor<'a, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl <'a, T: Clone + PartialEq + Send> SharedReactor<'a, T> {
+    pub fn new() -> Self {
+        Self::from(SendReactor::new())
+    }
+
+    // Locks the reactor for the lifetime of the returned guard, for building the graph, or for
+    // performing several operations without other threads interleaving with them.
+    //
+    // Panics if another thread panicked while holding the lock, as a propagation may have been
+    // left halfway through.
+    pub fn lock(&self) -> MutexGuard<'_, SendReactor<'a, T>> {
+        self.reactor.lock().expect("a thread panicked while holding the reactor's lock")
+    }
+
+    // Retrieves the current value of the cell, see `Reactor::value`.
+    pub fn value(&self, id: CellID) -> Option<T> {
+        self.lock().value(id)
+    }
+
+    // Sets the value of the specified input cell, see `Reactor::set_value`.
+    pub fn set_value(&self, id: CellID, new_value: T) -> Result<(), ReactError> {
+        self.lock().set_value(id, new_value)
+    }
+}
+
+impl <'a, T> From<SendReactor<'a, T>> for SharedReactor<'a, T> {
+    fn from(reactor: SendReactor<'a, T>) -> Self {
+        SharedReactor {
+            reactor: Arc::new(Mutex::new(reactor)),
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use std::any::{self, Any};
+use std::fmt;
+use std::marker::PhantomData;
+
+use {CallbackID, CellID, ReactError, Reactor, RemovalMode, Snapshot, Transaction};
+
+// The type-erased operations `AnyValue` needs from the value it holds.
+trait ErasedValue: Any {
+    fn clone_boxed(&self) -> Box<dyn ErasedValue>;
+    fn eq_erased(&self, other: &dyn ErasedValue) -> bool;
+    fn as_any(&self) -> &dyn Any;
+    fn type_name(&self) -> &'static str;
+}
+
+impl <T: Any + Clone + PartialEq> ErasedValue for T {
+    fn clone_boxed(&self) -> Box<dyn ErasedValue> {
+        Box::new(self.clone())
+    }
+
+    fn eq_erased(&self, other: &dyn ErasedValue) -> bool {
+        other.as_any().downcast_ref::<T>().is_some_and(|other| self == other)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        any::type_name::<T>()
+    }
+}
+
+// A value of any type, as stored by the cells of a `TypedReactor`.
+//
+// Two values are equal if they're of the same type, and are equal as values of that type.
+pub struct AnyValue(Box<dyn ErasedValue>);
+
+impl AnyValue {
+    pub fn new<T: Any + Clone + PartialEq>(value: T) -> Self {
+        AnyValue(Box::new(value))
+    }
+
+    // Gets a reference to the held value, or None if it isn't of type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.as_any().downcast_ref()
+    }
+}
+
+impl Clone for AnyValue {
+    fn clone(&self) -> Self {
+        AnyValue(self.0.clone_boxed())
+    }
+}
+
+impl PartialEq for AnyValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_erased(&*other.0)
+    }
+}
+
+impl fmt::Debug for AnyValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AnyValue({})", self.0.type_name())
+    }
+}
+
+// A handle to a cell of a `TypedReactor`, which knows the type of the cell's value.
+pub trait CellHandle: Copy {
+    type Value: Any + Clone + PartialEq;
+
+    fn id(&self) -> CellID;
+}
+
+// A handle to an input cell holding values of type `T`.
+pub struct InputHandle<T> {
+    id: CellID,
+    marker: PhantomData<fn() -> T>,
+}
+
+// A handle to a compute cell holding values of type `T`.
+pub struct ComputeHandle<T> {
+    id: CellID,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl <T> Clone for InputHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl <T> Copy for InputHandle<T> {}
+
+impl <T> fmt::Debug for InputHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "InputHandle<{}>({:?})", any::type_name::<T>(), self.id)
+    }
+}
+
+impl <T: Any + Clone + PartialEq> CellHandle for InputHandle<T> {
+    type Value = T;
+
+    fn id(&self) -> CellID {
+        self.id
+    }
+}
+
+impl <T> Clone for ComputeHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl <T> Copy for ComputeHandle<T> {}
+
+impl <T> fmt::Debug for ComputeHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ComputeHandle<{}>({:?})", any::type_name::<T>(), self.id)
+    }
+}
+
+impl <T: Any + Clone + PartialEq> CellHandle for ComputeHandle<T> {
+    type Value = T;
+
+    fn id(&self) -> CellID {
+        self.id
+    }
+}
+
+type ErasedComputeFunc = Box<dyn Fn(&[&AnyValue]) -> AnyValue>;
+
+// A tuple of handles to the dependencies of a compute cell, where `F` is a compute function taking
+// references to the values of these dependencies, in order, and returning an `R`.
+pub trait Dependencies<F, R> {
+    fn ids(&self) -> Vec<CellID>;
+
+    // Checks that the dependencies hold values of the types of their handles, see `check_type`.
+    fn check_types(&self, reactor: &Reactor<AnyValue>) -> Result<(), ReactError>;
+
+    // Wraps the compute function into one working with the type-erased values of the reactor.
+    fn erase(compute_func: F) -> ErasedComputeFunc;
+}
+
+macro_rules! impl_dependencies {
+    ($($handle:ident $ix:tt),+) => {
+        impl <$($handle: CellHandle,)+ F, R> Dependencies<F, R> for ($($handle,)+)
+            where F: 'static + Fn($(&$handle::Value),+) -> R,
+                  R: Any + Clone + PartialEq
+        {
+            fn ids(&self) -> Vec<CellID> {
+                vec![$(self.$ix.id()),+]
+            }
+
+            fn check_types(&self, reactor: &Reactor<AnyValue>) -> Result<(), ReactError> {
+                $(check_type::<$handle::Value>(reactor, self.$ix.id())?;)+
+                Ok(())
+            }
+
+            fn erase(compute_func: F) -> ErasedComputeFunc {
+                // the types of the dependencies are checked before the compute function is given to
+                // the reactor, and never change, so the downcasts can't fail
+                Box::new(move |values| AnyValue::new(compute_func($(
+                    values[$ix].downcast_ref::<$handle::Value>().unwrap()
+                ),+)))
+            }
+        }
+    };
+}
+
+impl_dependencies!(A 0);
+impl_dependencies!(A 0, B 1);
+impl_dependencies!(A 0, B 1, C 2);
+impl_dependencies!(A 0, B 1, C 2, D 3);
+impl_dependencies!(A 0, B 1, C 2, D 3, E 4);
+impl_dependencies!(A 0, B 1, C 2, D 3, E 4, G 5);
+
+// Checks that the specified cell holds values of type `T`, as a handle may be used with another
+// reactor than the one it was created by, where the cell with its ID may hold any type.
+//
+// Cells in an error state are let through, as their type can't be told.
+fn check_type<T: Any>(reactor: &Reactor<AnyValue>, id: CellID) -> Result<(), ReactError> {
+    let node = reactor.node(id)?;
+    match reactor.dep_graph[node].value() {
+        Ok(value) if value.downcast_ref::<T>().is_none() => Err(ReactError::UnexpectedType {
+            id,
+            expected: any::type_name::<T>(),
+            found: value.0.type_name(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+// A Reactor whose cells may hold values of different types.
+//
+// Cells are referred to by typed handles rather than plain `CellID`s, so mixing up the types of
+// cells, or setting the value of a compute cell, is caught at compile time. Handles used with
+// another reactor than their own are caught when they're used, with an `UnexpectedType` error.
+//
+// Only a subset of the Reactor API is available: creating, rewiring and removing cells, values,
+// comparators, plain callbacks, transactions, snapshots and the undo history. Fallible cells,
+// change callbacks, named functions and saving, labels, graph queries, `explain`, observers and
+// statistics are only available through a plain Reactor.
+#[derive(Debug, Default)]
+pub struct TypedReactor<'a> {
+    reactor: Reactor<'a, AnyValue>,
+}
+
+impl <'a> TypedReactor<'a> {
+    pub fn new() -> Self {
+        TypedReactor {
+            reactor: Reactor::new(),
+        }
+    }
+
+    // Creates an input cell with the specified initial value.
+    pub fn create_input<T: Any + Clone + PartialEq>(&mut self, initial: T) -> InputHandle<T> {
+        InputHandle {
+            id: self.reactor.create_input(AnyValue::new(initial)),
+            marker: PhantomData,
+        }
+    }
+
+    // Creates a compute cell from a tuple of handles to its dependencies, and a compute function
+    // taking references to their values as separate arguments, in the same order.
+    //
+    // Return an Err if any dependency doesn't exist.
+    pub fn create_compute<D, F, R>(&mut self, dependencies: D, compute_func: F) -> Result<ComputeHandle<R>, ReactError>
+        where D: Dependencies<F, R>
+    {
+        dependencies.check_types(&self.reactor)?;
+        let id = self.reactor.create_compute(&dependencies.ids(), D::erase(compute_func))?;
+        Ok(ComputeHandle {
+            id,
+            marker: PhantomData,
+        })
+    }
+
+    // Creates a lazy compute cell, see `Reactor::create_lazy_compute`.
+    pub fn create_lazy_compute<D, F, R>(&mut self, dependencies: D, compute_func: F) -> Result<ComputeHandle<R>, ReactError>
+        where D: Dependencies<F, R>
+    {
+        dependencies.check_types(&self.reactor)?;
+        let id = self.reactor.create_lazy_compute(&dependencies.ids(), D::erase(compute_func))?;
+        Ok(ComputeHandle {
+            id,
+            marker: PhantomData,
+        })
+    }
+
+    // Replaces the dependencies and compute function of a compute cell, see
+    // `Reactor::set_dependencies`. The new compute function must produce values of the same type.
+    pub fn set_dependencies<D, F, R>(&mut self, cell: ComputeHandle<R>, dependencies: D, compute_func: F) -> Result<(), ReactError>
+        where D: Dependencies<F, R>, R: Any
+    {
+        check_type::<R>(&self.reactor, cell.id)?;
+        dependencies.check_types(&self.reactor)?;
+        self.reactor.set_dependencies(cell.id, &dependencies.ids(), D::erase(compute_func))
+    }
+
+    // Replaces how a compute cell detects changes, see `Reactor::set_comparator`.
+    pub fn set_comparator<T, F>(&mut self, cell: ComputeHandle<T>, comparator: F) -> Result<(), ReactError>
+        where T: Any + Clone + PartialEq, F: 'static + Fn(&T, &T) -> bool
+    {
+        check_type::<T>(&self.reactor, cell.id)?;
+        self.reactor.set_comparator(cell.id, move |old, new| {
+            comparator(old.downcast_ref().unwrap(), new.downcast_ref().unwrap())
+        })
+    }
+
+    // Retrieves the current value of the cell, or None if the cell does not exist.
+    pub fn value<H: CellHandle>(&self, cell: H) -> Option<H::Value> {
+        self.reactor.node(cell.id()).ok()
+            .and_then(|node| self.reactor.current_value(node).ok())
+            .and_then(|value| value.downcast_ref().cloned())
+    }
+
+    // Sets the value of the specified input cell.
+    //
+    // Return an Err if the cell does not exist, or doesn't hold values of type `T`.
+    pub fn set_value<T: Any + Clone + PartialEq>(&mut self, cell: InputHandle<T>, new_value: T) -> Result<(), ReactError> {
+        check_type::<T>(&self.reactor, cell.id)?;
+        self.reactor.set_value(cell.id, AnyValue::new(new_value))
+    }
+
+    // Captures the values of all input cells, see `Reactor::snapshot`.
+    pub fn snapshot(&self) -> Snapshot<AnyValue> {
+        self.reactor.snapshot()
+    }
+
+    // Writes the values captured by a snapshot back, see `Reactor::restore`.
+    pub fn restore(&mut self, snapshot: &Snapshot<AnyValue>) -> Result<(), ReactError> {
+        self.reactor.restore(snapshot)
+    }
+
+    // Enables undoing changes to input cells, see `Reactor::set_history_depth`.
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.reactor.set_history_depth(depth)
+    }
+
+    // Reverts the latest change to input cells, see `Reactor::undo`.
+    pub fn undo(&mut self) -> Result<bool, ReactError> {
+        self.reactor.undo()
+    }
+
+    // Reapplies the latest undone change, see `Reactor::redo`.
+    pub fn redo(&mut self) -> Result<bool, ReactError> {
+        self.reactor.redo()
+    }
+
+    // Tells whether there's a change to undo, see `Reactor::can_undo`.
+    pub fn can_undo(&self) -> bool {
+        self.reactor.can_undo()
+    }
+
+    // Tells whether there's an undone change to redo, see `Reactor::can_redo`.
+    pub fn can_redo(&self) -> bool {
+        self.reactor.can_redo()
+    }
+
+    // Begins a transaction, see `Reactor::transaction`.
+    pub fn transaction<'r>(&'r mut self) -> TypedTransaction<'r, 'a> {
+        TypedTransaction {
+            transaction: self.reactor.transaction(),
+        }
+    }
+
+    // Removes the specified cell, see `Reactor::remove_cell`.
+    pub fn remove_cell<H: CellHandle>(&mut self, cell: H, mode: RemovalMode) -> Result<Vec<CellID>, ReactError> {
+        self.reactor.remove_cell(cell.id(), mode)
+    }
+
+    // Adds a callback to the specified cell, see `Reactor::add_callback`.
+    pub fn add_callback<H, F>(&mut self, cell: H, mut callback: F) -> Result<CallbackID, ReactError>
+        where H: CellHandle, F: FnMut(&H::Value) + 'a
+    {
+        check_type::<H::Value>(&self.reactor, cell.id())?;
+        self.reactor.add_callback(cell.id(), move |value| callback(value.downcast_ref().unwrap()))
+    }
+
+    // Removes the specified callback, see `Reactor::remove_callback`.
+    pub fn remove_callback<H: CellHandle>(&mut self, cell: H, callback: CallbackID) -> Result<(), ReactError> {
+        self.reactor.remove_callback(cell.id(), callback)
+    }
+}
+
+// A batch of input writes against a TypedReactor, see `Transaction`.
+pub struct TypedTransaction<'r, 'a: 'r> {
+    transaction: Transaction<'r, 'a, AnyValue>,
+}
+
+impl <'r, 'a> TypedTransaction<'r, 'a> {
+    // Stages a new value for the specified input cell.
+    pub fn set_value<T: Any + Clone + PartialEq>(&mut self, cell: InputHandle<T>, new_value: T) -> Result<(), ReactError> {
+        check_type::<T>(self.transaction.reactor, cell.id)?;
+        self.transaction.set_value(cell.id, AnyValue::new(new_value))
+    }
+
+    // Retrieves the value of the cell as seen by this transaction.
+    pub fn value<H: CellHandle>(&self, cell: H) -> Option<H::Value> {
+        self.transaction.value(cell.id()).and_then(|value| value.downcast_ref().cloned())
+    }
+
+    // Applies all staged writes, propagating them in a single pass.
+    pub fn commit(self) -> Result<(), ReactError> {
+        self.transaction.commit()
+    }
+
+    // Discards all staged writes.
+    pub fn rollback(self) {
+        self.transaction.rollback()
+    }
+}
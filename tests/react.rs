@@ -1,5 +1,10 @@
+// The cases below mirror the upstream exercise, which passes dependencies as `&vec![..]`.
+#![allow(clippy::useless_vec)]
+
 extern crate react;
 extern crate petgraph;
+#[macro_use]
+extern crate failure;
 use react::*;
 
 #[test]
@@ -116,7 +121,7 @@ fn compute_cells_fire_callbacks() {
         let mut reactor = Reactor::new();
         let input = reactor.create_input(1);
         let output = reactor.create_compute(&vec![input], |v| v[0] + 1).unwrap();
-        assert!(reactor.add_callback(output, |v| values.push(v)).is_ok());
+        assert!(reactor.add_callback(output, |v| values.push(*v)).is_ok());
         assert!(reactor.set_value(input, 3).is_ok());
     }
     assert_eq!(values, vec![4]);
@@ -127,7 +132,7 @@ fn error_adding_callback_to_nonexistent_cell() {
     let mut dummy_reactor = Reactor::new();
     let input = dummy_reactor.create_input(1);
     let output = dummy_reactor.create_compute(&vec![input], |_| 0).unwrap();
-    assert!(Reactor::new().add_callback(output, |_: usize| println!("hi")).is_err());
+    assert!(Reactor::new().add_callback(output, |_: &usize| println!("hi")).is_err());
 }
 
 #[test]
@@ -136,8 +141,8 @@ fn callbacks_only_fire_on_change() {
     {
         let mut reactor = Reactor::new();
         let input = reactor.create_input(1);
-        let output = reactor.create_compute(&vec![input], |v| if v[0] < 3 { 111 } else { 222 }).unwrap();
-        assert!(reactor.add_callback(output, |v| values.push(v)).is_ok());
+        let output = reactor.create_compute(&vec![input], |v| if *v[0] < 3 { 111 } else { 222 }).unwrap();
+        assert!(reactor.add_callback(output, |v| values.push(*v)).is_ok());
         assert!(reactor.set_value(input, 2).is_ok());
         assert!(reactor.set_value(input, 4).is_ok());
     }
@@ -153,11 +158,11 @@ fn callbacks_can_be_added_and_removed() {
         let mut reactor = Reactor::new();
         let input = reactor.create_input(11);
         let output = reactor.create_compute(&vec![input], |v| v[0] + 1).unwrap();
-        let callback = reactor.add_callback(output, |v| values1.push(v)).unwrap();
-        assert!(reactor.add_callback(output, |v| values2.push(v)).is_ok());
+        let callback = reactor.add_callback(output, |v| values1.push(*v)).unwrap();
+        assert!(reactor.add_callback(output, |v| values2.push(*v)).is_ok());
         assert!(reactor.set_value(input, 31).is_ok());
         assert!(reactor.remove_callback(output, callback).is_ok());
-        assert!(reactor.add_callback(output, |v| values3.push(v)).is_ok());
+        assert!(reactor.add_callback(output, |v| values3.push(*v)).is_ok());
         assert!(reactor.set_value(input, 41).is_ok());
     }
     assert_eq!(values1, vec![32]);
@@ -173,8 +178,8 @@ fn removing_a_callback_multiple_times_doesnt_interfere_with_other_callbacks() {
         let mut reactor = Reactor::new();
         let input = reactor.create_input(1);
         let output = reactor.create_compute(&vec![input], |v| v[0] + 1).unwrap();
-        let callback = reactor.add_callback(output, |v| values1.push(v)).unwrap();
-        assert!(reactor.add_callback(output, |v| values2.push(v)).is_ok());
+        let callback = reactor.add_callback(output, |v| values1.push(*v)).unwrap();
+        assert!(reactor.add_callback(output, |v| values2.push(*v)).is_ok());
         // We want the first remove to be Ok, but we don't care about the others.
         assert!(reactor.remove_callback(output, callback).is_ok());
         for _ in 1..5 {
@@ -196,7 +201,7 @@ fn callbacks_should_only_be_called_once_even_if_multiple_dependencies_change() {
         let minus_one1 = reactor.create_compute(&vec![input], |v| v[0] - 1).unwrap();
         let minus_one2 = reactor.create_compute(&vec![minus_one1], |v| v[0] - 1).unwrap();
         let output = reactor.create_compute(&vec![plus_one, minus_one2], |v| v[0] * v[1]).unwrap();
-        assert!(reactor.add_callback(output, |v| values.push(v)).is_ok());
+        assert!(reactor.add_callback(output, |v| values.push(*v)).is_ok());
         assert!(reactor.set_value(input, 4).is_ok());
     }
     assert_eq!(values, vec![10]);
@@ -213,7 +218,7 @@ fn callbacks_should_not_be_called_if_dependencies_change_but_output_value_doesnt
         let always_two = reactor.create_compute(&vec![plus_one, minus_one], |v| v[0] - v[1]).unwrap();
         println!("We're interested in id {}", always_two.index());
         let always_two_val = reactor.value(always_two);
-        assert!(reactor.add_callback(always_two, |v| values.push(v)).is_ok());
+        assert!(reactor.add_callback(always_two, |v| values.push(*v)).is_ok());
         for i in 2..5 {
             assert_eq!(reactor.value(always_two), always_two_val);
             assert!(reactor.set_value(input, i).is_ok());
@@ -234,9 +239,9 @@ fn test_adder_with_boolean_values() {
     let a_xor_b = reactor.create_compute(&vec![a, b], |v| v[0] ^ v[1]).unwrap();
     let sum = reactor.create_compute(&vec![a_xor_b, carry_in], |v| v[0] ^ v[1]).unwrap();
 
-    let a_xor_b_and_cin = reactor.create_compute(&vec![a_xor_b, carry_in], |v| v[0] && v[1]).unwrap();
-    let a_and_b = reactor.create_compute(&vec![a, b], |v| v[0] && v[1]).unwrap();
-    let carry_out = reactor.create_compute(&vec![a_xor_b_and_cin, a_and_b], |v| v[0] || v[1]).unwrap();
+    let a_xor_b_and_cin = reactor.create_compute(&vec![a_xor_b, carry_in], |v| *v[0] && *v[1]).unwrap();
+    let a_and_b = reactor.create_compute(&vec![a, b], |v| *v[0] && *v[1]).unwrap();
+    let carry_out = reactor.create_compute(&vec![a_xor_b_and_cin, a_and_b], |v| *v[0] || *v[1]).unwrap();
 
     let tests = vec![
         (false, false, false, false, false),
@@ -258,3 +263,913 @@ fn test_adder_with_boolean_values() {
         assert_eq!(reactor.value(carry_out), Some(expected_cout));
     }
 }
+
+#[test]
+fn compute_cells_are_recomputed_once_without_observing_intermediate_states() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let mut reactor = Reactor::new();
+    let input = reactor.create_input(1);
+    let plus_one = reactor.create_compute(&[input], |v| v[0] + 1).unwrap();
+    let times_two = reactor.create_compute(&[plus_one], |v| v[0] * 2).unwrap();
+    let seen_by_output = seen.clone();
+    let output = reactor.create_compute(&[input, times_two], move |v| {
+        seen_by_output.borrow_mut().push((*v[0], *v[1]));
+        v[0] + v[1]
+    }).unwrap();
+    seen.borrow_mut().clear();
+    assert!(reactor.set_value(input, 5).is_ok());
+    assert_eq!(*seen.borrow(), vec![(5, 12)]);
+    assert_eq!(reactor.value(output), Some(17));
+}
+
+#[test]
+fn transactions_propagate_once_on_commit() {
+    let mut values = Vec::new();
+    {
+        let mut reactor = Reactor::new();
+        let a = reactor.create_input(1);
+        let b = reactor.create_input(2);
+        let sum = reactor.create_compute(&[a, b], |v| v[0] + v[1]).unwrap();
+        assert!(reactor.add_callback(sum, |v| values.push(*v)).is_ok());
+        {
+            let mut transaction = reactor.transaction();
+            assert!(transaction.set_value(a, 10).is_ok());
+            assert!(transaction.set_value(b, 20).is_ok());
+            assert!(transaction.set_value(b, 30).is_ok());
+            assert_eq!(transaction.value(b), Some(30));
+            assert_eq!(transaction.value(sum), Some(3));
+            assert!(transaction.commit().is_ok());
+        }
+        assert_eq!(reactor.value(sum), Some(40));
+    }
+    assert_eq!(values, vec![40]);
+}
+
+#[test]
+fn transactions_write_their_inputs_in_the_order_of_their_ids() {
+    use std::cell::RefCell;
+    let changed = RefCell::new(Vec::new());
+    {
+        let mut reactor = Reactor::new();
+        let inputs = (0..5).map(|i| reactor.create_input(i)).collect::<Vec<_>>();
+        for &input in &inputs {
+            assert!(reactor.add_change_callback(input, |id, _, _| changed.borrow_mut().push(id)).is_ok());
+        }
+        let mut transaction = reactor.transaction();
+        for &input in inputs.iter().rev() {
+            assert!(transaction.set_value(input, 10).is_ok());
+        }
+        assert!(transaction.commit().is_ok());
+    }
+    let changed = changed.into_inner();
+    let mut sorted = changed.clone();
+    sorted.sort();
+    assert_eq!((changed.len(), changed), (5, sorted));
+}
+
+#[test]
+fn rolled_back_transactions_leave_the_reactor_untouched() {
+    let mut values = Vec::new();
+    {
+        let mut reactor = Reactor::new();
+        let input = reactor.create_input(1);
+        let output = reactor.create_compute(&[input], |v| v[0] + 1).unwrap();
+        assert!(reactor.add_callback(output, |v| values.push(*v)).is_ok());
+        {
+            let mut transaction = reactor.transaction();
+            assert!(transaction.set_value(input, 5).is_ok());
+            assert!(transaction.set_value(output, 5).is_err());
+            transaction.rollback();
+        }
+        {
+            let mut transaction = reactor.transaction();
+            assert!(transaction.set_value(input, 6).is_ok());
+        }
+        assert_eq!(reactor.value(input), Some(1));
+        assert_eq!(reactor.value(output), Some(2));
+    }
+    assert_eq!(values, Vec::new());
+}
+
+#[test]
+fn cells_with_dependants_are_not_removed_in_restrict_mode() {
+    let mut reactor = Reactor::new();
+    let input = reactor.create_input(1);
+    let output = reactor.create_compute(&[input, input], |v| v[0] + v[1]).unwrap();
+    match reactor.remove_cell(input, RemovalMode::Restrict) {
+        Err(ReactError::CellInUse { id, dependants, .. }) => {
+            assert_eq!(id, input);
+            assert_eq!(dependants, vec![output]);
+        },
+        other => panic!("expected CellInUse, got {:?}", other),
+    }
+    assert_eq!(reactor.remove_cell(output, RemovalMode::Restrict).unwrap(), vec![output]);
+    assert_eq!(reactor.value(output), None);
+    assert!(reactor.set_value(input, 2).is_ok());
+    assert_eq!(reactor.remove_cell(input, RemovalMode::Restrict).unwrap(), vec![input]);
+}
+
+#[test]
+fn cascading_removal_keeps_the_remaining_ids_valid() {
+    let mut values = Vec::new();
+    {
+        let mut reactor = Reactor::new();
+        let first = reactor.create_input(1);
+        let second = reactor.create_input(2);
+        let plus_one = reactor.create_compute(&[first], |v| v[0] + 1).unwrap();
+        let sum = reactor.create_compute(&[plus_one, second], |v| v[0] + v[1]).unwrap();
+        let doubled = reactor.create_compute(&[second], |v| v[0] * 2).unwrap();
+        assert!(reactor.add_callback(doubled, |v| values.push(*v)).is_ok());
+
+        let mut removed = reactor.remove_cell(first, RemovalMode::Cascade).unwrap();
+        removed.sort();
+        assert_eq!(removed, vec![first, plus_one, sum]);
+        for &id in &[first, plus_one, sum] {
+            assert_eq!(reactor.value(id), None);
+        }
+
+        // the freed graph nodes may be recycled, but never under the removed IDs
+        let replacement = reactor.create_input(10);
+        assert_eq!(reactor.value(replacement), Some(10));
+        match reactor.set_value(first, 3) {
+            Err(ReactError::RemovedCell { id }) => assert_eq!(id, first),
+            other => panic!("expected RemovedCell, got {:?}", other),
+        }
+        assert!(reactor.create_compute(&[second, sum], |v| *v[0]).is_err());
+
+        assert!(reactor.set_value(second, 5).is_ok());
+        assert_eq!(reactor.value(doubled), Some(10));
+    }
+    assert_eq!(values, vec![10]);
+}
+
+#[test]
+fn cells_can_hold_non_copy_values() {
+    let mut lengths = Vec::new();
+    {
+        let mut reactor = Reactor::new();
+        let first = reactor.create_input(String::from("Hello"));
+        let second = reactor.create_input(String::from("world"));
+        let greeting = reactor.create_compute(&[first, second], |v| format!("{}, {}!", v[0], v[1])).unwrap();
+        assert!(reactor.add_callback(greeting, |v: &String| lengths.push(v.len())).is_ok());
+        assert!(reactor.set_value(second, String::from("reactor")).is_ok());
+        assert_eq!(reactor.value(greeting), Some(String::from("Hello, reactor!")));
+    }
+    assert_eq!(lengths, vec![15]);
+
+    let mut reactor = Reactor::new();
+    let samples = reactor.create_input(vec![1.0, 2.0, 3.0]);
+    let doubled = reactor.create_compute(&[samples], |v| v[0].iter().map(|x| x * 2.0).collect()).unwrap();
+    assert!(reactor.set_value(samples, vec![0.5]).is_ok());
+    assert_eq!(reactor.value(doubled), Some(vec![1.0]));
+}
+
+#[test]
+fn propagation_does_not_clone_values() {
+    use std::cell::Cell;
+    thread_local!(static CLONES: Cell<usize> = const { Cell::new(0) });
+
+    #[derive(Debug, PartialEq)]
+    struct Tracked(u32);
+    impl Clone for Tracked {
+        fn clone(&self) -> Self {
+            CLONES.with(|clones| clones.set(clones.get() + 1));
+            Tracked(self.0)
+        }
+    }
+
+    let mut reactor = Reactor::new();
+    let input = reactor.create_input(Tracked(1));
+    let plus_one = reactor.create_compute(&[input], |v| Tracked(v[0].0 + 1)).unwrap();
+    let sum = reactor.create_compute(&[input, plus_one], |v| Tracked(v[0].0 + v[1].0)).unwrap();
+    assert!(reactor.add_callback(sum, |_| ()).is_ok());
+    assert!(reactor.set_value(input, Tracked(5)).is_ok());
+    assert_eq!(CLONES.with(|clones| clones.get()), 0);
+    assert_eq!(reactor.value(sum), Some(Tracked(11)));
+}
+
+#[test]
+fn typed_reactors_hold_cells_of_different_types() {
+    let mut changes = Vec::new();
+    {
+        let mut reactor = TypedReactor::new();
+        reactor.set_history_depth(1);
+        let count = reactor.create_input(3u32);
+        let name = reactor.create_input(String::from("abc"));
+        let fits = reactor.create_compute((count, name), |count, name| name.len() as u32 <= *count).unwrap();
+        let description = reactor.create_compute((fits, name), |fits: &bool, name: &String| {
+            format!("{} {}", name, if *fits { "fits" } else { "doesn't fit" })
+        }).unwrap();
+        assert_eq!(reactor.value(fits), Some(true));
+        assert!(reactor.add_callback(fits, |fits: &bool| changes.push(*fits)).is_ok());
+
+        assert!(reactor.set_value(name, String::from("abcd")).is_ok());
+        assert_eq!(reactor.value(fits), Some(false));
+        assert_eq!(reactor.value(description), Some(String::from("abcd doesn't fit")));
+
+        {
+            let mut transaction = reactor.transaction();
+            assert!(transaction.set_value(count, 10).is_ok());
+            assert!(transaction.set_value(name, String::from("abcdefg")).is_ok());
+            assert!(transaction.commit().is_ok());
+        }
+        assert_eq!(reactor.value(description), Some(String::from("abcdefg fits")));
+        assert_eq!(reactor.value(count), Some(10));
+        assert!(reactor.can_undo() && !reactor.can_redo());
+    }
+    assert_eq!(changes, vec![false, true]);
+}
+
+#[test]
+fn handles_used_with_another_typed_reactor_are_rejected() {
+    let mut other = TypedReactor::new();
+    let count = other.create_input(3u32);
+
+    let mut reactor = TypedReactor::new();
+    let name = reactor.create_input(String::from("abc"));
+    let length = reactor.create_compute((name,), |name: &String| name.len()).unwrap();
+    match reactor.set_value(count, 4) {
+        Err(ReactError::UnexpectedType { id, .. }) => assert_eq!(id, name.id()),
+        other => panic!("expected UnexpectedType, got {:?}", other),
+    }
+    assert!(reactor.create_compute((count,), |count: &u32| *count + 1).is_err());
+    assert!(reactor.add_callback(count, |_| ()).is_err());
+    assert!(reactor.transaction().set_value(count, 4).is_err());
+    assert_eq!(reactor.value(name), Some(String::from("abc")));
+    assert_eq!(reactor.value(length), Some(3));
+}
+
+#[test]
+fn lazy_compute_cells_are_only_recomputed_on_demand() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    let computations = Rc::new(Cell::new(0));
+    let mut reactor = Reactor::new();
+    let input = reactor.create_input(1);
+    let counter = computations.clone();
+    let lazy = reactor.create_lazy_compute(&[input], move |v| {
+        counter.set(counter.get() + 1);
+        v[0] * 10
+    }).unwrap();
+    let plus_one = reactor.create_lazy_compute(&[lazy], |v| v[0] + 1).unwrap();
+    computations.set(0);
+
+    for i in 2..5 {
+        assert!(reactor.set_value(input, i).is_ok());
+    }
+    assert_eq!(computations.get(), 0);
+    assert_eq!(reactor.value(lazy), Some(40));
+    assert_eq!(reactor.value(lazy), Some(40));
+    assert_eq!(computations.get(), 1);
+    assert_eq!(reactor.value(plus_one), Some(41));
+    assert_eq!(computations.get(), 1);
+}
+
+#[test]
+fn lazy_compute_cells_are_recomputed_when_needed_by_callbacks() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    let computations = Rc::new(Cell::new(0));
+    let mut output_values = Vec::new();
+    let mut lazy_values = Vec::new();
+    {
+        let mut reactor = Reactor::new();
+        let input = reactor.create_input(1);
+        let counter = computations.clone();
+        let lazy = reactor.create_lazy_compute(&[input], move |v| {
+            counter.set(counter.get() + 1);
+            v[0] * 10
+        }).unwrap();
+        let output = reactor.create_compute(&[lazy, input], |v| v[0] + v[1]).unwrap();
+        assert!(reactor.add_callback(output, |v| output_values.push(*v)).is_ok());
+        assert!(reactor.set_value(input, 2).is_ok());
+        assert_eq!(computations.get(), 2);
+
+        assert!(reactor.add_callback(lazy, |v| lazy_values.push(*v)).is_ok());
+        assert!(reactor.set_value(input, 3).is_ok());
+        assert_eq!(computations.get(), 3);
+    }
+    assert_eq!(output_values, vec![22, 33]);
+    assert_eq!(lazy_values, vec![30]);
+}
+
+#[test]
+fn shared_reactors_can_be_used_from_several_threads() {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let sums = Arc::new(Mutex::new(Vec::new()));
+    let reactor = SharedReactor::new();
+    let (inputs, sum) = {
+        let mut reactor = reactor.lock();
+        let inputs = (0..4).map(|_| reactor.create_input(0)).collect::<Vec<_>>();
+        let sum = reactor.create_compute(&inputs, |v| v.iter().cloned().sum()).unwrap();
+        let sums = sums.clone();
+        assert!(reactor.add_callback(sum, move |v| sums.lock().unwrap().push(*v)).is_ok());
+        (inputs, sum)
+    };
+    assert_send_sync(&reactor);
+
+    let threads = inputs.into_iter().map(|input| {
+        let reactor = reactor.clone();
+        thread::spawn(move || {
+            for i in 1..=10 {
+                assert!(reactor.set_value(input, i).is_ok());
+            }
+        })
+    }).collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert_eq!(reactor.value(sum), Some(40));
+    let sums = sums.lock().unwrap();
+    // every propagation completes before the next one starts, so the sum grows by one each time
+    assert_eq!(*sums, (1..=40).collect::<Vec<_>>());
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_reactors_fire_callbacks_on_the_calling_thread() {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    {
+        let mut reactor = SendReactor::new_parallel();
+        let input = reactor.create_input(1u64);
+        let cells = (0..64u64).map(|i| reactor.create_compute(&[input], move |v| v[0] * i).unwrap()).collect::<Vec<_>>();
+        let sum = reactor.create_compute(&cells, |v| v.iter().cloned().sum()).unwrap();
+        for &cell in cells.iter().chain(Some(&sum)) {
+            let calls = calls.clone();
+            assert!(reactor.add_callback(cell, move |&v| calls.lock().unwrap().push((v, thread::current().id()))).is_ok());
+        }
+        assert!(reactor.set_value(input, 2).is_ok());
+        assert_eq!(reactor.value(sum), Some(2 * (0..64).sum::<u64>()));
+    }
+    let calls = calls.lock().unwrap();
+    // the cell multiplying by zero doesn't change
+    assert_eq!(calls.len(), 64);
+    assert!(calls.iter().all(|&(_, id)| id == thread::current().id()));
+}
+
+#[test]
+fn compute_cells_can_be_given_new_dependencies() {
+    let mut values = Vec::new();
+    {
+        let mut reactor = Reactor::new();
+        let input = reactor.create_input(1);
+        let other = reactor.create_input(100);
+        let plus_one = reactor.create_compute(&[input], |v| v[0] + 1).unwrap();
+        let times_two = reactor.create_compute(&[plus_one], |v| v[0] * 2).unwrap();
+        let output = reactor.create_compute(&[input], |v| v[0] * 10).unwrap();
+        let sum = reactor.create_compute(&[output, input], |v| v[0] + v[1]).unwrap();
+        assert!(reactor.add_callback(sum, |v| values.push(*v)).is_ok());
+
+        // the formula's result doesn't change, so no callbacks are fired
+        assert!(reactor.set_dependencies(output, &[input], |v| v[0] * 10).is_ok());
+        assert!(reactor.set_dependencies(output, &[times_two, other], |v| v[0] + v[1]).is_ok());
+        assert_eq!(reactor.value(output), Some(104));
+        assert_eq!(reactor.value(sum), Some(105));
+
+        assert!(reactor.set_value(input, 2).is_ok());
+        assert_eq!(reactor.value(sum), Some(108));
+        assert!(reactor.set_value(other, 0).is_ok());
+        assert_eq!(reactor.value(sum), Some(8));
+    }
+    assert_eq!(values, vec![105, 108, 8]);
+}
+
+#[test]
+fn error_giving_a_compute_cell_dependencies_that_depend_on_it() {
+    let mut reactor = Reactor::new();
+    let input = reactor.create_input(1);
+    let plus_one = reactor.create_compute(&[input], |v| v[0] + 1).unwrap();
+    let times_two = reactor.create_compute(&[plus_one], |v| v[0] * 2).unwrap();
+    match reactor.set_dependencies(plus_one, &[input, times_two], |v| v[0] + v[1]) {
+        Err(ReactError::DependencyCycle { id, cyclic_deps, .. }) => {
+            assert_eq!(id, plus_one);
+            assert_eq!(cyclic_deps, vec![times_two]);
+        },
+        other => panic!("expected DependencyCycle, got {:?}", other),
+    }
+    assert!(reactor.set_dependencies(plus_one, &[plus_one], |v| *v[0]).is_err());
+    assert!(reactor.set_dependencies(input, &[], |_| 0).is_err());
+
+    // the cell is left untouched
+    assert!(reactor.set_value(input, 5).is_ok());
+    assert_eq!(reactor.value(times_two), Some(12));
+}
+
+#[test]
+fn errors_propagate_to_dependants_until_the_failing_cell_recovers() {
+    let mut results = Vec::new();
+    let quotient;
+    {
+        let mut reactor = Reactor::new();
+        let dividend = reactor.create_input(12i32);
+        let divisor = reactor.create_input(3);
+        quotient = reactor.create_fallible_compute(&[dividend, divisor], |v| {
+            v[0].checked_div(*v[1]).ok_or_else(|| format_err!("division by zero"))
+        }).unwrap();
+        let plus_one = reactor.create_compute(&[quotient], |v| v[0] + 1).unwrap();
+        assert!(reactor.add_result_callback(plus_one, |v| {
+            results.push(v.copied().map_err(|e| (e.origin(), e.error().to_string())))
+        }).is_ok());
+        assert_eq!(reactor.value(plus_one), Some(5));
+
+        assert!(reactor.set_value(divisor, 0).is_ok());
+        assert_eq!(reactor.value(quotient), None);
+        assert_eq!(reactor.value(plus_one), None);
+        let error = reactor.result(plus_one).unwrap().unwrap_err();
+        assert_eq!(error.origin(), quotient);
+        assert_eq!(reactor.result(quotient), Some(Err(error)));
+        // failing again in the same way doesn't notify the dependants
+        assert!(reactor.set_value(dividend, 10).is_ok());
+
+        assert!(reactor.set_value(divisor, 2).is_ok());
+        assert_eq!(reactor.value(plus_one), Some(6));
+    }
+    assert_eq!(results, vec![Err((quotient, "division by zero".to_string())), Ok(6)]);
+}
+
+#[test]
+fn plain_callbacks_are_only_called_with_values() {
+    let mut values = Vec::new();
+    {
+        let mut reactor = Reactor::new();
+        let input = reactor.create_input(16.0);
+        let root = reactor.create_fallible_compute(&[input], |v: &[&f64]| {
+            if *v[0] >= 0.0 { Ok(v[0].sqrt()) } else { Err(format_err!("negative input")) }
+        }).unwrap();
+        assert!(reactor.add_callback(root, |v| values.push(*v)).is_ok());
+        assert!(reactor.set_value(input, -1.0).is_ok());
+        assert!(reactor.set_value(input, 9.0).is_ok());
+    }
+    assert_eq!(values, vec![3.0]);
+}
+
+#[test]
+fn input_callbacks_fire_along_with_compute_callbacks() {
+    let mut calls = Vec::new();
+    {
+        let calls = ::std::cell::RefCell::new(&mut calls);
+        let mut reactor = Reactor::new();
+        let input = reactor.create_input(1);
+        let parity = reactor.create_compute(&[input], |v| v[0] % 2).unwrap();
+        assert!(reactor.add_callback(input, |v| calls.borrow_mut().push(("input", *v))).is_ok());
+        assert!(reactor.add_callback(parity, |v| calls.borrow_mut().push(("parity", *v))).is_ok());
+
+        assert!(reactor.set_value(input, 3).is_ok());
+        // the value didn't change
+        assert!(reactor.set_value(input, 3).is_ok());
+        {
+            let mut transaction = reactor.transaction();
+            assert!(transaction.set_value(input, 5).is_ok());
+            assert!(transaction.set_value(input, 4).is_ok());
+            assert!(transaction.commit().is_ok());
+        }
+    }
+    assert_eq!(calls, vec![("input", 3), ("input", 4), ("parity", 0)]);
+}
+
+#[test]
+fn change_callbacks_receive_the_previous_value() {
+    let mut changes = Vec::new();
+    {
+        let changes = ::std::cell::RefCell::new(&mut changes);
+        let mut reactor = Reactor::new();
+        let input = reactor.create_input(1);
+        let times_two = reactor.create_compute(&[input], |v| v[0] * 2).unwrap();
+        let times_four = reactor.create_compute(&[times_two], |v| v[0] * 2).unwrap();
+        for &cell in &[input, times_four] {
+            assert!(reactor.add_change_callback(cell, |id, old, new| {
+                changes.borrow_mut().push((id, *old.unwrap(), *new.unwrap()))
+            }).is_ok());
+        }
+        assert!(reactor.set_value(input, 3).is_ok());
+        {
+            let mut transaction = reactor.transaction();
+            assert!(transaction.set_value(input, 4).is_ok());
+            assert!(transaction.set_value(input, 5).is_ok());
+            assert!(transaction.commit().is_ok());
+        }
+        assert_eq!(**changes.borrow(), vec![
+            (input, 1, 3), (times_four, 4, 12),
+            (input, 3, 5), (times_four, 12, 20),
+        ]);
+    }
+}
+
+#[test]
+fn comparators_decide_whether_compute_cells_changed() {
+    let mut rounded = Vec::new();
+    let mut recomputed = Vec::new();
+    {
+        let mut reactor = Reactor::new();
+        let input = reactor.create_input(1.0);
+        let third = reactor.create_compute(&[input], |v: &[&f64]| v[0] / 3.0).unwrap();
+        let doubled = reactor.create_compute(&[input], |v: &[&f64]| v[0] * 2.0).unwrap();
+        assert!(reactor.set_comparator(third, |old, new| (old - new).abs() < 0.01).is_ok());
+        assert!(reactor.set_comparator(doubled, |_, _| false).is_ok());
+        assert!(reactor.add_callback(third, |v| rounded.push((v * 100.0).round())).is_ok());
+        assert!(reactor.add_callback(doubled, |v| recomputed.push(*v)).is_ok());
+
+        assert!(reactor.set_value(input, 1.001).is_ok());
+        // the cell keeps its old value when it isn't considered changed
+        assert_eq!(reactor.value(third), Some(1.0 / 3.0));
+        assert!(reactor.set_value(input, 3.0).is_ok());
+        assert!(reactor.set_comparator(input, |_, _| false).is_err());
+    }
+    assert_eq!(rounded, vec![100.0]);
+    assert_eq!(recomputed, vec![2.002, 6.0]);
+}
+
+#[test]
+fn comparators_of_lazy_cells_decide_whether_they_changed() {
+    let mut values = Vec::new();
+    {
+        let mut reactor = Reactor::new();
+        let input = reactor.create_input(1.0);
+        let lazy = reactor.create_lazy_compute(&[input], |v: &[&f64]| *v[0]).unwrap();
+        let doubled = reactor.create_compute(&[lazy], |v: &[&f64]| v[0] * 2.0).unwrap();
+        assert!(reactor.set_comparator(lazy, |old, new| (old - new).abs() < 0.5).is_ok());
+        assert!(reactor.add_callback(doubled, |v| values.push(*v)).is_ok());
+
+        assert!(reactor.set_value(input, 1.1).is_ok());
+        assert!(reactor.set_value(input, 1.2).is_ok());
+        assert_eq!(reactor.value(lazy), Some(1.0));
+        assert!(reactor.set_value(input, 2.0).is_ok());
+    }
+    assert_eq!(values, vec![4.0]);
+}
+
+#[test]
+fn unchanged_cells_stop_the_propagation() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    let calls = Rc::new(Cell::new(0));
+    let mut reactor = Reactor::new();
+    let input = reactor.create_input(5);
+    let clamped = reactor.create_compute(&[input], |v| *v[0].min(&10)).unwrap();
+    let expensive = {
+        let calls = calls.clone();
+        reactor.create_compute(&[clamped], move |v| {
+            calls.set(calls.get() + 1);
+            v[0] * 2
+        }).unwrap()
+    };
+    let _below = reactor.create_compute(&[expensive], |v| v[0] + 1).unwrap();
+    assert_eq!(calls.get(), 1);
+
+    assert!(reactor.set_value(input, 20).is_ok());
+    assert_eq!(calls.get(), 2);
+    assert!(reactor.set_value(input, 30).is_ok());
+    assert!(reactor.set_value(input, 40).is_ok());
+    assert_eq!(calls.get(), 2);
+    assert!(reactor.set_value(input, 7).is_ok());
+    assert_eq!(calls.get(), 3);
+    assert_eq!(reactor.value(expensive), Some(14));
+}
+
+#[test]
+fn very_deep_chains_of_cells_do_not_overflow_the_stack() {
+    const DEPTH: usize = 100_000;
+    let mut values = Vec::new();
+    {
+        let mut reactor = Reactor::new();
+        let input = reactor.create_input(0);
+        let mut eager = input;
+        let mut lazy = input;
+        for _ in 0..DEPTH {
+            eager = reactor.create_compute(&[eager], |v| v[0] + 1).unwrap();
+            lazy = reactor.create_lazy_compute(&[lazy], |v| v[0] + 1).unwrap();
+        }
+        assert!(reactor.add_callback(eager, |v| values.push(*v)).is_ok());
+        assert!(reactor.set_value(input, 1).is_ok());
+        assert_eq!(reactor.value(lazy), Some(DEPTH + 1));
+        assert!(reactor.remove_cell(input, RemovalMode::Cascade).is_ok());
+    }
+    assert_eq!(values, vec![DEPTH + 1]);
+}
+
+#[test]
+fn changes_to_input_cells_can_be_undone_and_redone() {
+    let mut values = Vec::new();
+    {
+        let mut reactor = Reactor::new();
+        reactor.set_history_depth(2);
+        let a = reactor.create_input(1);
+        let b = reactor.create_input(10);
+        let sum = reactor.create_compute(&[a, b], |v| v[0] + v[1]).unwrap();
+        assert!(reactor.add_callback(sum, |v| values.push(*v)).is_ok());
+
+        assert!(reactor.set_value(a, 2).is_ok());
+        {
+            let mut transaction = reactor.transaction();
+            assert!(transaction.set_value(a, 3).is_ok());
+            assert!(transaction.set_value(b, 20).is_ok());
+            assert!(transaction.commit().is_ok());
+        }
+        assert!(reactor.set_value(b, 30).is_ok());
+
+        // only the two latest changes are remembered
+        assert_eq!(reactor.undo().ok(), Some(true));
+        assert_eq!(reactor.value(sum), Some(23));
+        assert_eq!(reactor.undo().ok(), Some(true));
+        assert_eq!((reactor.value(a), reactor.value(b)), (Some(2), Some(10)));
+        assert_eq!(reactor.undo().ok(), Some(false));
+
+        assert_eq!(reactor.redo().ok(), Some(true));
+        assert_eq!(reactor.value(sum), Some(23));
+        // a new change discards the changes that could be redone
+        assert!(reactor.set_value(a, 5).is_ok());
+        assert!(!reactor.can_redo());
+        assert_eq!(reactor.redo().ok(), Some(false));
+    }
+    assert_eq!(values, vec![12, 23, 33, 23, 12, 23, 25]);
+}
+
+#[test]
+fn snapshots_restore_all_input_values_at_once() {
+    let mut values = Vec::new();
+    {
+        let mut reactor = Reactor::new();
+        let a = reactor.create_input(1);
+        let b = reactor.create_input(2);
+        let sum = reactor.create_compute(&[a, b], |v| v[0] + v[1]).unwrap();
+        let snapshot = reactor.snapshot();
+        assert!(reactor.set_value(a, 10).is_ok());
+        assert!(reactor.set_value(b, 20).is_ok());
+        assert!(reactor.add_callback(sum, |v| values.push(*v)).is_ok());
+
+        assert!(reactor.restore(&snapshot).is_ok());
+        assert_eq!((reactor.value(a), reactor.value(b)), (Some(1), Some(2)));
+        // nothing changes when restoring the same values again
+        assert!(reactor.restore(&snapshot).is_ok());
+    }
+    assert_eq!(values, vec![3]);
+}
+
+#[test]
+fn restoring_a_snapshot_onto_a_compute_cell_changes_nothing() {
+    let mut other = Reactor::new();
+    let _ = other.create_input(1);
+    let _ = other.create_input(2);
+    let snapshot = other.snapshot();
+
+    let mut reactor = Reactor::new();
+    let input = reactor.create_input(10);
+    let plus_one = reactor.create_compute(&[input], |v| v[0] + 1).unwrap();
+    match reactor.restore(&snapshot) {
+        Err(ReactError::ExpectedInputCell { id, .. }) => assert_eq!(id, plus_one),
+        other => panic!("expected ExpectedInputCell, got {:?}", other),
+    }
+    assert_eq!((reactor.value(input), reactor.value(plus_one)), (Some(10), Some(11)));
+}
+
+#[test]
+fn compute_cells_remember_the_name_of_their_function() {
+    let mut reactor = Reactor::new();
+    reactor.register_function("sum", |v: &[&i32]| v.iter().cloned().sum());
+    reactor.register_function("max", |v: &[&i32]| v.iter().cloned().cloned().max().unwrap_or(0));
+    let a = reactor.create_input(1);
+    let b = reactor.create_input(2);
+    let sum = reactor.create_compute_named(&[a, b], "sum").unwrap();
+    let max = reactor.create_lazy_compute_named(&[a, b], "max").unwrap();
+    assert_eq!((reactor.value(sum), reactor.value(max)), (Some(3), Some(2)));
+    assert_eq!((reactor.function_name(sum), reactor.function_name(max)), (Some("sum"), Some("max")));
+    assert_eq!(reactor.function_name(a), None);
+
+    assert!(reactor.set_dependencies_named(sum, &[a, b, max], "sum").is_ok());
+    assert_eq!(reactor.value(sum), Some(5));
+    assert!(reactor.set_dependencies(sum, &[a], |v| v[0] * 10).is_ok());
+    assert_eq!(reactor.function_name(sum), None);
+    match reactor.create_compute_named(&[a], "min") {
+        Err(ReactError::UnknownFunction { name }) => assert_eq!(name, "min"),
+        other => panic!("expected UnknownFunction, got {:?}", other),
+    }
+}
+
+#[test]
+fn reactors_can_be_rendered_as_dot_graphs() {
+    let mut reactor = Reactor::new();
+    reactor.register_function("sum", |v: &[&i32]| v.iter().cloned().sum());
+    let a = reactor.create_input(1);
+    let b = reactor.create_input(2);
+    let sum = reactor.create_compute_named(&[b, a], "sum").unwrap();
+    let _lazy = reactor.create_lazy_compute(&[sum], |v| v[0] * 2).unwrap();
+    assert!(reactor.add_callback(sum, |_| ()).is_ok());
+    assert!(reactor.set_value(a, 3).is_ok());
+    let dot = reactor.to_dot();
+    assert!(dot.starts_with("digraph {\n"));
+    assert!(dot.contains("n0 [shape=box, label=\"#0\\n= 3\"];"));
+    assert!(dot.contains("n2 [shape=ellipse, label=\"#2 sum\\n= 5\\ncallbacks: 1\"];"));
+    assert!(dot.contains("n3 [shape=ellipse, label=\"#3\\n= 6 (stale)\"];"));
+    assert!(dot.contains("n1 -> n2 [label=\"0\"];"));
+    assert!(dot.contains("n0 -> n2 [label=\"1\"];"));
+}
+
+#[test]
+fn cells_can_be_labelled_and_found_by_their_label() {
+    let mut reactor = Reactor::new();
+    let price = reactor.create_input(10);
+    let total = reactor.create_compute(&[price], |v| v[0] * 2).unwrap();
+    assert!(reactor.set_label(price, "price").is_ok());
+    assert!(reactor.set_label(total, "total").is_ok());
+    assert_eq!(reactor.cell_by_name("price"), Some(price));
+    assert_eq!(reactor.label(total), Some("total"));
+    match reactor.set_label(price, "total") {
+        Err(ReactError::DuplicateLabel { label, id }) => assert_eq!((label.as_str(), id), ("total", total)),
+        other => panic!("expected DuplicateLabel, got {:?}", other),
+    }
+
+    let error = reactor.set_value(total, 3).unwrap_err();
+    assert!(error.to_string().contains("(\"total\")"), "{}", error);
+    let error = reactor.remove_cell(price, RemovalMode::Restrict).unwrap_err();
+    assert!(error.to_string().ends_with("(\"total\")]"), "{}", error);
+    assert!(format!("{:?}", reactor).contains("label: Some(\"price\")"));
+
+    assert!(reactor.set_label(total, "doubled").is_ok());
+    assert_eq!(reactor.cell_by_name("total"), None);
+    assert!(reactor.remove_cell(total, RemovalMode::Restrict).is_ok());
+    assert_eq!(reactor.cell_by_name("doubled"), None);
+    assert!(reactor.clear_label(price).is_ok());
+    assert_eq!((reactor.cell_by_name("price"), reactor.label(price)), (None, None));
+}
+
+#[test]
+fn the_dependency_graph_can_be_queried() {
+    let mut reactor = Reactor::new();
+    let a = reactor.create_input(1);
+    let b = reactor.create_input(2);
+    let unused = reactor.create_input(3);
+    let sum = reactor.create_compute(&[b, a], |v| v[0] + v[1]).unwrap();
+    let double = reactor.create_lazy_compute(&[sum, sum], |v| v[0] + v[1]).unwrap();
+    let plus_a = reactor.create_compute(&[double, a], |v| v[0] + v[1]).unwrap();
+    assert_eq!(reactor.dependencies(sum).unwrap(), vec![b, a]);
+    assert_eq!(reactor.dependencies(a).unwrap(), vec![]);
+    assert_eq!(reactor.direct_dependants(a).unwrap(), vec![sum, plus_a]);
+    assert_eq!(reactor.direct_dependants(sum).unwrap(), vec![double]);
+    assert_eq!(reactor.transitive_dependants(b).unwrap(), vec![sum, double, plus_a]);
+    assert_eq!(reactor.upstream_inputs(plus_a).unwrap(), vec![a, b]);
+    assert_eq!(reactor.upstream_inputs(unused).unwrap(), vec![unused]);
+    assert_eq!(reactor.cell_kind(a).unwrap(), CellKind::Input);
+    assert_eq!(reactor.cell_kind(sum).unwrap(), CellKind::Computed);
+    assert_eq!(reactor.cell_kind(double).unwrap(), CellKind::LazyComputed);
+
+    let first = reactor.add_callback(sum, |_| ()).unwrap();
+    let second = reactor.add_callback(sum, |_| ()).unwrap();
+    assert_eq!(reactor.callback_ids(sum).unwrap(), vec![first, second]);
+    assert!(reactor.remove_callback(sum, first).is_ok());
+    assert_eq!(reactor.callback_ids(sum).unwrap(), vec![second]);
+    assert!(reactor.remove_cell(plus_a, RemovalMode::Restrict).is_ok());
+    match reactor.dependencies(plus_a) {
+        Err(ReactError::RemovedCell { id }) => assert_eq!(id, plus_a),
+        other => panic!("expected RemovedCell, got {:?}", other),
+    }
+}
+
+#[test]
+fn changes_can_be_traced_back_to_the_input_causing_them() {
+    let mut reactor = Reactor::new();
+    let a = reactor.create_input(1);
+    let b = reactor.create_input(2);
+    let sum = reactor.create_compute(&[a, b], |v| v[0] + v[1]).unwrap();
+    let doubled = reactor.create_lazy_compute(&[sum], |v| v[0] * 2).unwrap();
+    let plus_b = reactor.create_compute(&[doubled, b], |v| v[0] + v[1]).unwrap();
+    let is_big = reactor.create_compute(&[sum], |v| if *v[0] > 10 { 1 } else { 0 }).unwrap();
+    assert!(reactor.explain(plus_b).unwrap().is_empty());
+
+    assert!(reactor.set_value(a, 3).is_ok());
+    assert_eq!(reactor.explain(plus_b).unwrap(), vec![plus_b, doubled, sum, a]);
+    assert_eq!(reactor.explain(a).unwrap(), vec![a]);
+    assert!(reactor.explain(b).unwrap().is_empty());
+    assert!(reactor.explain(is_big).unwrap().is_empty());
+
+    assert!(reactor.set_value(b, 10).is_ok());
+    assert_eq!(reactor.explain(plus_b).unwrap(), vec![plus_b, doubled, sum, b]);
+    assert_eq!(reactor.explain(is_big).unwrap(), vec![is_big, sum, b]);
+    assert!(reactor.explain(a).unwrap().is_empty());
+    // writing the value an input already holds isn't a propagation, so it doesn't clear the trace
+    assert!(reactor.set_value(b, 10).is_ok());
+    assert_eq!(reactor.explain(is_big).unwrap(), vec![is_big, sum, b]);
+
+    assert!(reactor.set_dependencies(sum, &[a], |v| *v[0]).is_ok());
+    assert_eq!(reactor.explain(is_big).unwrap(), vec![is_big, sum]);
+}
+
+// Records the events of a reactor as text, for checking them once they happened.
+struct EventLog(std::rc::Rc<std::cell::RefCell<Vec<String>>>);
+
+impl EventLog {
+    fn log(&self, event: String) {
+        self.0.borrow_mut().push(event);
+    }
+}
+
+fn indices(cells: &[CellID]) -> Vec<usize> {
+    cells.iter().map(CellID::index).collect()
+}
+
+impl ReactorObserver<i32> for EventLog {
+    fn propagation_started(&mut self, sources: &[CellID]) {
+        self.log(format!("started {:?}", indices(sources)));
+    }
+
+    fn propagation_finished(&mut self, changed: &[CellID]) {
+        self.log(format!("finished {:?}", indices(changed)));
+    }
+
+    fn cell_recomputed(&mut self, cell: CellID, old_value: Result<&i32, &CellError>, new_value: Result<&i32, &CellError>, _: std::time::Duration) {
+        self.log(format!("recomputed {} from {:?} to {:?}", cell.index(), old_value.ok(), new_value.ok()));
+    }
+
+    fn callback_invoked(&mut self, cell: CellID, _: CallbackID) {
+        self.log(format!("called back {}", cell.index()));
+    }
+
+    fn input_created(&mut self, cell: CellID) {
+        self.log(format!("input {}", cell.index()));
+    }
+
+    fn compute_created(&mut self, cell: CellID, dependencies: &[CellID]) {
+        self.log(format!("compute {} of {:?}", cell.index(), indices(dependencies)));
+    }
+
+    fn cell_removed(&mut self, cell: CellID) {
+        self.log(format!("removed {}", cell.index()));
+    }
+}
+
+#[test]
+fn observers_are_told_about_the_events_of_the_reactor() {
+    let events = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let mut reactor = Reactor::new();
+    reactor.set_observer(Box::new(EventLog(events.clone())));
+    let a = reactor.create_input(1);
+    let plus_one = reactor.create_compute(&[a], |v| v[0] + 1).unwrap();
+    let doubled = reactor.create_lazy_compute(&[plus_one], |v| v[0] * 2).unwrap();
+    let unchanged = reactor.create_compute(&[a], |_| 0).unwrap();
+    assert!(reactor.add_callback(plus_one, |_| ()).is_ok());
+    assert!(reactor.set_value(a, 2).is_ok());
+    assert_eq!(reactor.value(doubled), Some(6));
+    assert!(reactor.set_value(a, 2).is_ok());
+    assert!(reactor.remove_cell(unchanged, RemovalMode::Restrict).is_ok());
+    assert_eq!(*events.borrow(), vec![
+        "input 0",
+        "compute 1 of [0]",
+        "compute 2 of [1]",
+        "compute 3 of [0]",
+        "started [0]",
+        "recomputed 1 from Some(2) to Some(3)",
+        "recomputed 3 from Some(0) to Some(0)",
+        "called back 1",
+        "finished [0, 1]",
+        "recomputed 2 from Some(4) to Some(6)",
+        "removed 3",
+    ]);
+
+    assert!(reactor.take_observer().is_some());
+    assert!(reactor.set_value(a, 3).is_ok());
+    assert_eq!(events.borrow().len(), 11);
+}
+
+#[test]
+fn statistics_show_which_cells_are_the_most_expensive() {
+    let mut reactor = Reactor::new();
+    let a = reactor.create_input(1);
+    let cheap = reactor.create_compute(&[a], |v| v[0] + 1).unwrap();
+    let slow = reactor.create_compute(&[a], |v| {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        v[0] * 2
+    }).unwrap();
+    let constant = reactor.create_compute(&[a], |_| 0).unwrap();
+    assert!(reactor.add_callback(cheap, |_| ()).is_ok());
+    assert!(reactor.add_callback(cheap, |_| ()).is_ok());
+    assert_eq!(reactor.cell_stats(slow), None);
+
+    reactor.set_stats_enabled(true);
+    assert!(reactor.set_value(a, 2).is_ok());
+    assert!(reactor.set_value(a, 3).is_ok());
+    let slow_stats = reactor.cell_stats(slow).unwrap();
+    assert_eq!((slow_stats.recomputes, slow_stats.unchanged_recomputes), (2, 0));
+    assert!(slow_stats.compute_time >= std::time::Duration::from_millis(20));
+    let constant_stats = reactor.cell_stats(constant).unwrap();
+    assert_eq!((constant_stats.recomputes, constant_stats.unchanged_recomputes), (2, 2));
+    assert_eq!(reactor.cell_stats(cheap).unwrap().callback_calls, 4);
+    let most_expensive = reactor.most_expensive_cells(2);
+    assert_eq!(most_expensive.len(), 2);
+    assert_eq!(most_expensive[0], (slow, slow_stats));
+
+    reactor.reset_stats();
+    assert_eq!(reactor.cell_stats(slow), Some(CellStats::default()));
+    assert!(reactor.most_expensive_cells(2).is_empty());
+    reactor.set_stats_enabled(false);
+    assert!(reactor.set_value(a, 4).is_ok());
+    assert_eq!(reactor.cell_stats(slow), None);
+}
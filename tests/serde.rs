@@ -0,0 +1,77 @@
+// Saving and loading needs serde_json, whose `PartialEq` impls would break type inference in the
+// other tests, so these live in their own crate.
+#![cfg(feature = "serde")]
+
+extern crate react;
+extern crate serde_json;
+use react::*;
+
+#[test]
+fn reactors_can_be_saved_and_loaded_again() {
+    let registry = || {
+        let mut registry = ComputeRegistry::new();
+        registry.register("sum", |v: &[&i32]| v.iter().cloned().sum());
+        registry.register("double", |v: &[&i32]| v[0] * 2);
+        registry
+    };
+    let mut reactor = Reactor::with_registry(registry());
+    let a = reactor.create_input(1);
+    let removed = reactor.create_input(0);
+    let b = reactor.create_input(2);
+    let sum = reactor.create_compute_named(&[a, b], "sum").unwrap();
+    let double = reactor.create_compute_named(&[sum], "double").unwrap();
+    assert!(reactor.remove_cell(removed, RemovalMode::Restrict).is_ok());
+    assert!(reactor.set_value(b, 5).is_ok());
+    assert!(reactor.set_label(double, "double").is_ok());
+    let json = serde_json::to_string(&reactor.save().unwrap()).unwrap();
+
+    let mut loaded = Reactor::load(serde_json::from_str(&json).unwrap(), registry()).unwrap();
+    assert_eq!(loaded.value(double), Some(12));
+    assert_eq!(loaded.cell_by_name("double"), Some(double));
+    assert!(loaded.set_value(a, 10).is_ok());
+    assert_eq!(loaded.value(double), Some(30));
+    assert!(loaded.value(removed).is_none());
+    let c = loaded.create_input(3);
+    assert_ne!(c, removed);
+
+    match Reactor::<i32>::load(serde_json::from_str(&json).unwrap(), ComputeRegistry::new()) {
+        Err(ReactError::UnknownFunction { name }) => assert_eq!(name, "sum"),
+        other => panic!("expected UnknownFunction, got {:?}", other.map(|_| ())),
+    }
+    let unnamed = reactor.create_compute(&[a], |v| v[0] + 1).unwrap();
+    match reactor.save() {
+        Err(ReactError::UnnamedFunction { id, .. }) => assert_eq!(id, unnamed),
+        other => panic!("expected UnnamedFunction, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn saves_with_cells_at_unused_indices_are_rejected() {
+    for &index in &[300_000u64, 5_000_000_000] {
+        let json = format!(r#"{{"cells":[{{"index":{},"generation":0,"kind":{{"Input":{{"value":1}}}}}}],"next_generation":1}}"#, index);
+        match Reactor::<i32>::load(serde_json::from_str(&json).unwrap(), ComputeRegistry::new()) {
+            Err(ReactError::InvalidSave { .. }) => {},
+            other => panic!("expected InvalidSave, got {:?}", other.map(|_| ())),
+        }
+    }
+}
+
+#[test]
+fn send_reactors_can_be_saved_and_loaded_again() {
+    let registry = || {
+        let mut registry = ComputeRegistry::new();
+        registry.register("double", |v: &[&i32]| v[0] * 2);
+        registry
+    };
+    let mut reactor = SendReactor::with_registry(registry());
+    let a = reactor.create_input(1);
+    let double = reactor.create_compute_named(&[a], "double").unwrap();
+    assert!(reactor.set_value(a, 4).is_ok());
+    let json = serde_json::to_string(&reactor.save().unwrap()).unwrap();
+
+    let loaded = SendReactor::<i32>::load(serde_json::from_str(&json).unwrap(), registry()).unwrap();
+    let shared = SharedReactor::from(loaded);
+    let handle = shared.clone();
+    std::thread::spawn(move || assert!(handle.set_value(a, 5).is_ok())).join().unwrap();
+    assert_eq!(shared.value(double), Some(10));
+}